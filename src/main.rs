@@ -1,8 +1,26 @@
-use reader::ClassFileReader;
+use reader::{ClassFile, ClassFileReader};
 
 mod reader;
 mod vm;
 fn main() {
-    let mut reader = ClassFileReader::new("Main.class");
-    println!("Classfile: {:#?}", reader.read().unwrap());
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.as_slice() {
+        [flag, path] if flag == "--dump-class" => print!("{}", read_class(path).pretty_print()),
+        [] => println!("Classfile: {:#?}", read_class("Main.class")),
+        _ => {
+            eprintln!("Usage: jvm-r [--dump-class <path>]");
+            std::process::exit(2);
+        }
+    }
+}
+
+/// Reads the class at `path`, exiting with status 1 if it can't be opened or parsed.
+fn read_class(path: &str) -> ClassFile {
+    match ClassFileReader::new(path).and_then(|mut reader| reader.read()) {
+        Ok(class) => class,
+        Err(e) => {
+            eprintln!("Error reading {}: {}", path, e);
+            std::process::exit(1);
+        }
+    }
 }
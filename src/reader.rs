@@ -1,14 +1,17 @@
 #![allow(unused)]
 
 use std::{
-    fs::File,
-    io::{self, BufRead, BufReader, Cursor, Error, Read},
+    fmt::{self, Write},
+    io::{self, Cursor, Error, Read},
+    path::Path,
 };
 
 use bitflags::bitflags;
 use byteorder::{BigEndian, ReadBytesExt};
 use num_enum::{IntoPrimitive, TryFromPrimitive, TryFromPrimitiveError};
 
+use crate::vm::jvm::{Opcode, SwitchTable};
+
 #[derive(Default, Debug)]
 pub struct ClassFile {
     magic: u32,
@@ -20,7 +23,7 @@ pub struct ClassFile {
     this_class: u16,
     super_class: u16,
     interfaces_count: u16,
-    interfaces: Vec<ConstantPoolInfo>,
+    interfaces: Vec<u16>,
     fields_count: u16,
     fields: Vec<FieldInfo>,
     methods_count: u16,
@@ -89,6 +92,8 @@ pub enum ConstantPoolInfo {
     Package {
         name_index: u16,
     },
+    /// Index 0 and the slot following a Long or Double; never a valid reference target.
+    Unusable,
 }
 
 bitflags! {
@@ -133,6 +138,34 @@ bitflags! {
         const Strict = 0x0800;
         const Synthetic = 0x1000;
     }
+
+    #[derive(Default, Debug)]
+    struct ParameterAccessFlags: u16 {
+        const Final = 0x0010;
+        const Synthetic = 0x1000;
+        const Mandated = 0x8000;
+    }
+
+    #[derive(Default, Debug)]
+    struct ModuleFlags: u16 {
+        const Open = 0x0020;
+        const Synthetic = 0x1000;
+        const Mandated = 0x8000;
+    }
+
+    #[derive(Default, Debug)]
+    struct RequiresFlags: u16 {
+        const Transitive = 0x0020;
+        const StaticPhase = 0x0040;
+        const Synthetic = 0x1000;
+        const Mandated = 0x8000;
+    }
+
+    #[derive(Default, Debug)]
+    struct ExportsFlags: u16 {
+        const Synthetic = 0x1000;
+        const Mandated = 0x8000;
+    }
 }
 
 #[derive(Debug)]
@@ -169,7 +202,55 @@ struct ExceptionTable {
 }
 
 #[derive(Debug)]
-struct StackMapFrame {}
+enum VerificationTypeInfo {
+    Top,
+    Integer,
+    Float,
+    Double,
+    Long,
+    Null,
+    UninitializedThis,
+    Object { cpool_index: u16 },
+    Uninitialized { offset: u16 },
+}
+
+/// One entry of a `StackMapTable`, named after the JVMS 4.7.4 frame kinds.
+#[derive(Debug)]
+enum StackMapFrame {
+    SameFrame {
+        frame_type: u8,
+    },
+    SameLocals1StackItemFrame {
+        frame_type: u8,
+        stack: VerificationTypeInfo,
+    },
+    SameLocals1StackItemFrameExtended {
+        frame_type: u8,
+        offset_delta: u16,
+        stack: VerificationTypeInfo,
+    },
+    ChopFrame {
+        frame_type: u8,
+        offset_delta: u16,
+    },
+    SameFrameExtended {
+        frame_type: u8,
+        offset_delta: u16,
+    },
+    AppendFrame {
+        frame_type: u8,
+        offset_delta: u16,
+        locals: Vec<VerificationTypeInfo>,
+    },
+    FullFrame {
+        frame_type: u8,
+        offset_delta: u16,
+        number_of_locals: u16,
+        locals: Vec<VerificationTypeInfo>,
+        number_of_stack_items: u16,
+        stack: Vec<VerificationTypeInfo>,
+    },
+}
 
 #[derive(Debug)]
 struct BootstrapMethod {
@@ -184,6 +265,35 @@ struct LineNumberTable {
     line_number: u16,
 }
 
+#[derive(Debug)]
+struct MethodParameter {
+    name_index: u16,
+    access_flags: ParameterAccessFlags,
+}
+
+#[derive(Debug)]
+struct ModuleRequires {
+    requires_index: u16,
+    requires_flags: RequiresFlags,
+    requires_version_index: u16,
+}
+
+/// Shared layout of the `exports` and `opens` tables of a `Module` attribute.
+#[derive(Debug)]
+struct ModuleExports {
+    index: u16,
+    flags: ExportsFlags,
+    to_count: u16,
+    to_index: Vec<u16>,
+}
+
+#[derive(Debug)]
+struct ModuleProvides {
+    provides_index: u16,
+    provides_with_count: u16,
+    provides_with_index: Vec<u16>,
+}
+
 #[derive(Debug)]
 enum Attribute {
     ConstantValue {
@@ -230,10 +340,32 @@ enum Attribute {
         num_bootstrap_methods: u16,
         bootstrap_methods: Vec<BootstrapMethod>,
     },
-    MethodParameters {},
-    Module {},
-    ModulePackages {},
-    ModuleMainClass {},
+    MethodParameters {
+        parameters_count: u8,
+        parameters: Vec<MethodParameter>,
+    },
+    Module {
+        module_name_index: u16,
+        module_flags: ModuleFlags,
+        module_version_index: u16,
+        requires_count: u16,
+        requires: Vec<ModuleRequires>,
+        exports_count: u16,
+        exports: Vec<ModuleExports>,
+        opens_count: u16,
+        opens: Vec<ModuleExports>,
+        uses_count: u16,
+        uses_index: Vec<u16>,
+        provides_count: u16,
+        provides: Vec<ModuleProvides>,
+    },
+    ModulePackages {
+        package_count: u16,
+        package_index: Vec<u16>,
+    },
+    ModuleMainClass {
+        main_class_index: u16,
+    },
     NestHost {
         host_class_index: u16,
     },
@@ -250,34 +382,70 @@ enum Attribute {
 
 #[derive(Debug)]
 pub struct ClassFileReader {
-    buf: BufReader<File>,
+    buf: Cursor<Vec<u8>>,
     constant_pool: Vec<ConstantPoolInfo>,
+    in_code: bool,
 }
 
 impl ClassFileReader {
-    pub fn new(path: &str) -> Self {
-        let file = File::open(path).expect("Error opening file");
+    pub fn new(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(Self::from_bytes(std::fs::read(path)?))
+    }
+
+    pub fn from_bytes(bytes: Vec<u8>) -> Self {
         Self {
-            buf: BufReader::new(file),
+            buf: Cursor::new(bytes),
             constant_pool: Vec::new(),
+            in_code: false,
         }
     }
 
     pub fn read(&mut self) -> io::Result<ClassFile> {
         let magic = self.buf.read_u32::<BigEndian>()?;
+        if magic != 0xCAFEBABE {
+            return Err(self.report_error(&format!(
+                "ClassFormatError: Incompatible magic value {:#010X}",
+                magic
+            )));
+        }
         let minor_version = self.buf.read_u16::<BigEndian>()?;
         let major_version = self.buf.read_u16::<BigEndian>()?;
         let constant_pool_count = self.buf.read_u16::<BigEndian>()?;
-        let mut constant_pool = vec![ConstantPoolInfo::String { string_index: 0 }]; // unhinged jvm spec bs
-        for i in 0..constant_pool_count - 1 {
+        let mut constant_pool = vec![ConstantPoolInfo::Unusable]; // unhinged jvm spec bs
+        let mut i = 1;
+        while i < constant_pool_count {
             let tag = self.buf.read_u8()?;
-            constant_pool.push(self.constant_tag(tag)?);
+            let entry = self.constant_tag(tag)?;
+            let wide = matches!(
+                entry,
+                ConstantPoolInfo::Long { .. } | ConstantPoolInfo::Double { .. }
+            );
+            constant_pool.push(entry);
+            // 8-byte constants take up two entries in the pool
+            if wide {
+                constant_pool.push(ConstantPoolInfo::Unusable);
+                i += 1;
+            }
+            i += 1;
         }
         self.constant_pool = constant_pool.to_vec();
+        self.validate_constant_pool()?;
         let access_flags = ClassAccessFlags::from_bits(self.buf.read_u16::<BigEndian>()?)
             .ok_or_else(|| self.report_error("Expected class access flags, got invalid flag"))?;
         let this_class = self.buf.read_u16::<BigEndian>()?;
+        self.expect_class(this_class, "this_class")?;
         let super_class = self.buf.read_u16::<BigEndian>()?;
+        if super_class != 0 {
+            self.expect_class(super_class, "super_class")?;
+        } else if !access_flags.contains(ClassAccessFlags::Module)
+            && self.class_name(this_class) != Some("java/lang/Object")
+        {
+            // module-info is the only other class file without a superclass
+            return Err(self.report_error(&format!(
+                "ClassFormatError: {} has no superclass, only java/lang/Object may omit it",
+                self.class_name(this_class).unwrap_or("<invalid>")
+            )));
+        }
         let interfaces_count = self.buf.read_u16::<BigEndian>()?;
         let interfaces = self.read_interfaces(interfaces_count)?;
         let fields_count = self.buf.read_u16::<BigEndian>()?;
@@ -286,6 +454,12 @@ impl ClassFileReader {
         let methods = self.read_methods(methods_count)?;
         let attributes_count = self.buf.read_u16::<BigEndian>()?;
         let attributes = self.read_attributes(attributes_count)?;
+        if !self.remaining().is_empty() {
+            return Err(self.report_error(&format!(
+                "{} trailing bytes after the class attributes",
+                self.remaining().len()
+            )));
+        }
 
         Ok(ClassFile {
             magic,
@@ -314,8 +488,11 @@ impl ClassFileReader {
                 .ok_or_else(|| {
                     self.report_error("Expected method access flags, got invalid flag")
                 })?;
+            self.check_method_flags(&access_flags)?;
             let name_index = self.buf.read_u16::<BigEndian>()?;
+            self.expect_utf8(name_index, "name_index")?;
             let descriptor_index = self.buf.read_u16::<BigEndian>()?;
+            self.expect_utf8(descriptor_index, "descriptor_index")?;
             let attributes_count = self.buf.read_u16::<BigEndian>()?;
             let attributes = self.read_attributes(attributes_count)?;
             methods.push(MethodInfo {
@@ -329,6 +506,35 @@ impl ClassFileReader {
         Ok(methods)
     }
 
+    /// Rejects the field modifier combinations JVMS 4.5 forbids.
+    fn check_field_flags(&self, flags: &FieldAccessFlags) -> io::Result<()> {
+        use FieldAccessFlags as F;
+        let visibility = flags.bits() & (F::Public | F::Private | F::Protected).bits();
+        if visibility.count_ones() > 1 || flags.contains(F::Final | F::Volatile) {
+            return Err(self.report_error(&format!(
+                "ClassFormatError: Illegal field modifiers {:#06x}",
+                flags.bits()
+            )));
+        }
+        Ok(())
+    }
+
+    /// Rejects the method modifier combinations JVMS 4.6 forbids.
+    fn check_method_flags(&self, flags: &MethodAccessFlags) -> io::Result<()> {
+        use MethodAccessFlags as M;
+        let visibility = flags.bits() & (M::Public | M::Private | M::Protected).bits();
+        let abstract_conflicts = M::Private | M::Static | M::Final | M::Synchronized | M::Native;
+        if visibility.count_ones() > 1
+            || (flags.contains(M::Abstract) && flags.intersects(abstract_conflicts))
+        {
+            return Err(self.report_error(&format!(
+                "ClassFormatError: Illegal method modifiers {:#06x}",
+                flags.bits()
+            )));
+        }
+        Ok(())
+    }
+
     fn read_exception_table(&mut self, n: u16) -> io::Result<Vec<ExceptionTable>> {
         let mut tables = Vec::new();
         for _ in 0..n {
@@ -348,15 +554,23 @@ impl ClassFileReader {
     }
 
     fn read_code_attrib(&mut self) -> io::Result<Attribute> {
+        // Code attributes only belong to methods, never to another Code attribute.
+        // Rejecting nesting also bounds recursion on hostile input.
+        if self.in_code {
+            return Err(self.report_error("Code attribute nested inside another Code attribute"));
+        }
         let max_stack = self.buf.read_u16::<BigEndian>()?;
         let max_locals = self.buf.read_u16::<BigEndian>()?;
         let code_length = self.buf.read_u32::<BigEndian>()?;
-        let mut code = vec![0u8; code_length as usize];
-        self.buf.read_exact(&mut code)?;
+        let code = self.read_bytes(code_length as usize)?;
+        self.validate_code(&code)?;
         let exception_table_length = self.buf.read_u16::<BigEndian>()?;
         let exception_table = self.read_exception_table(exception_table_length)?;
         let attributes_count = self.buf.read_u16::<BigEndian>()?;
-        let attributes = self.read_attributes(attributes_count)?;
+        self.in_code = true;
+        let attributes = self.read_attributes(attributes_count);
+        self.in_code = false;
+        let attributes = attributes?;
         Ok(Attribute::Code {
             max_stack,
             max_locals,
@@ -369,6 +583,18 @@ impl ClassFileReader {
         })
     }
 
+    /// Walks the instructions in `code`, rejecting unknown or reserved opcodes,
+    /// truncated instructions and malformed switch tables.
+    fn validate_code(&self, code: &[u8]) -> io::Result<()> {
+        let mut pc = 0;
+        while pc < code.len() {
+            pc += Opcode::instruction_length(code, pc).map_err(|e| {
+                self.report_error(&format!("Invalid instruction at pc {}: {}", pc, e))
+            })?;
+        }
+        Ok(())
+    }
+
     fn read_line_number_table_attrib(&mut self) -> io::Result<Attribute> {
         let line_number_table_length = self.buf.read_u16::<BigEndian>()?;
         let mut line_number_table = Vec::new();
@@ -386,6 +612,208 @@ impl ClassFileReader {
         })
     }
 
+    fn read_stack_map_table_attrib(&mut self) -> io::Result<Attribute> {
+        let number_of_entries = self.buf.read_u16::<BigEndian>()?;
+        let mut entries = Vec::new();
+        for _ in 0..number_of_entries {
+            let frame_type = self.buf.read_u8()?;
+            let frame = match frame_type {
+                0..=63 => StackMapFrame::SameFrame { frame_type },
+                64..=127 => StackMapFrame::SameLocals1StackItemFrame {
+                    frame_type,
+                    stack: self.read_verification_type()?,
+                },
+                247 => StackMapFrame::SameLocals1StackItemFrameExtended {
+                    frame_type,
+                    offset_delta: self.buf.read_u16::<BigEndian>()?,
+                    stack: self.read_verification_type()?,
+                },
+                248..=250 => StackMapFrame::ChopFrame {
+                    frame_type,
+                    offset_delta: self.buf.read_u16::<BigEndian>()?,
+                },
+                251 => StackMapFrame::SameFrameExtended {
+                    frame_type,
+                    offset_delta: self.buf.read_u16::<BigEndian>()?,
+                },
+                252..=254 => {
+                    let offset_delta = self.buf.read_u16::<BigEndian>()?;
+                    let mut locals = Vec::new();
+                    for _ in 251..frame_type {
+                        locals.push(self.read_verification_type()?);
+                    }
+                    StackMapFrame::AppendFrame {
+                        frame_type,
+                        offset_delta,
+                        locals,
+                    }
+                }
+                255 => {
+                    let offset_delta = self.buf.read_u16::<BigEndian>()?;
+                    let number_of_locals = self.buf.read_u16::<BigEndian>()?;
+                    let mut locals = Vec::new();
+                    for _ in 0..number_of_locals {
+                        locals.push(self.read_verification_type()?);
+                    }
+                    let number_of_stack_items = self.buf.read_u16::<BigEndian>()?;
+                    let mut stack = Vec::new();
+                    for _ in 0..number_of_stack_items {
+                        stack.push(self.read_verification_type()?);
+                    }
+                    StackMapFrame::FullFrame {
+                        frame_type,
+                        offset_delta,
+                        number_of_locals,
+                        locals,
+                        number_of_stack_items,
+                        stack,
+                    }
+                }
+                reserved => {
+                    return Err(self.report_error(&format!(
+                        "Expected stack map frame type, received reserved type '{}'",
+                        reserved
+                    )))
+                }
+            };
+            entries.push(frame);
+        }
+        Ok(Attribute::StackMapTable {
+            number_of_entries,
+            entries,
+        })
+    }
+
+    fn read_verification_type(&mut self) -> io::Result<VerificationTypeInfo> {
+        use VerificationTypeInfo as V;
+        Ok(match self.buf.read_u8()? {
+            0 => V::Top,
+            1 => V::Integer,
+            2 => V::Float,
+            3 => V::Double,
+            4 => V::Long,
+            5 => V::Null,
+            6 => V::UninitializedThis,
+            7 => {
+                let cpool_index = self.buf.read_u16::<BigEndian>()?;
+                self.expect_class(cpool_index, "stack map object type")?;
+                V::Object { cpool_index }
+            }
+            8 => V::Uninitialized {
+                offset: self.buf.read_u16::<BigEndian>()?,
+            },
+            other => {
+                return Err(self.report_error(&format!(
+                    "Expected verification type tag, received '{}'",
+                    other
+                )))
+            }
+        })
+    }
+
+    fn read_method_parameters_attrib(&mut self) -> io::Result<Attribute> {
+        let parameters_count = self.buf.read_u8()?;
+        let mut parameters = Vec::new();
+        for _ in 0..parameters_count {
+            let name_index = self.buf.read_u16::<BigEndian>()?;
+            let access_flags = ParameterAccessFlags::from_bits(self.buf.read_u16::<BigEndian>()?)
+                .ok_or_else(|| {
+                self.report_error("Expected parameter access flags, got invalid flag")
+            })?;
+            parameters.push(MethodParameter {
+                name_index,
+                access_flags,
+            });
+        }
+        Ok(Attribute::MethodParameters {
+            parameters_count,
+            parameters,
+        })
+    }
+
+    fn read_u16_list(&mut self, n: u16) -> io::Result<Vec<u16>> {
+        let mut list = Vec::new();
+        for _ in 0..n {
+            list.push(self.buf.read_u16::<BigEndian>()?);
+        }
+        Ok(list)
+    }
+
+    fn read_module_exports(&mut self, n: u16) -> io::Result<Vec<ModuleExports>> {
+        let mut exports = Vec::new();
+        for _ in 0..n {
+            let index = self.buf.read_u16::<BigEndian>()?;
+            let flags = ExportsFlags::from_bits(self.buf.read_u16::<BigEndian>()?)
+                .ok_or_else(|| self.report_error("Expected exports flags, got invalid flag"))?;
+            let to_count = self.buf.read_u16::<BigEndian>()?;
+            let to_index = self.read_u16_list(to_count)?;
+            exports.push(ModuleExports {
+                index,
+                flags,
+                to_count,
+                to_index,
+            });
+        }
+        Ok(exports)
+    }
+
+    fn read_module_attrib(&mut self) -> io::Result<Attribute> {
+        let module_name_index = self.buf.read_u16::<BigEndian>()?;
+        let module_flags = ModuleFlags::from_bits(self.buf.read_u16::<BigEndian>()?)
+            .ok_or_else(|| self.report_error("Expected module flags, got invalid flag"))?;
+        let module_version_index = self.buf.read_u16::<BigEndian>()?;
+
+        let requires_count = self.buf.read_u16::<BigEndian>()?;
+        let mut requires = Vec::new();
+        for _ in 0..requires_count {
+            let requires_index = self.buf.read_u16::<BigEndian>()?;
+            let requires_flags = RequiresFlags::from_bits(self.buf.read_u16::<BigEndian>()?)
+                .ok_or_else(|| self.report_error("Expected requires flags, got invalid flag"))?;
+            let requires_version_index = self.buf.read_u16::<BigEndian>()?;
+            requires.push(ModuleRequires {
+                requires_index,
+                requires_flags,
+                requires_version_index,
+            });
+        }
+
+        let exports_count = self.buf.read_u16::<BigEndian>()?;
+        let exports = self.read_module_exports(exports_count)?;
+        let opens_count = self.buf.read_u16::<BigEndian>()?;
+        let opens = self.read_module_exports(opens_count)?;
+        let uses_count = self.buf.read_u16::<BigEndian>()?;
+        let uses_index = self.read_u16_list(uses_count)?;
+
+        let provides_count = self.buf.read_u16::<BigEndian>()?;
+        let mut provides = Vec::new();
+        for _ in 0..provides_count {
+            let provides_index = self.buf.read_u16::<BigEndian>()?;
+            let provides_with_count = self.buf.read_u16::<BigEndian>()?;
+            let provides_with_index = self.read_u16_list(provides_with_count)?;
+            provides.push(ModuleProvides {
+                provides_index,
+                provides_with_count,
+                provides_with_index,
+            });
+        }
+
+        Ok(Attribute::Module {
+            module_name_index,
+            module_flags,
+            module_version_index,
+            requires_count,
+            requires,
+            exports_count,
+            exports,
+            opens_count,
+            opens,
+            uses_count,
+            uses_index,
+            provides_count,
+            provides,
+        })
+    }
+
     fn read_attributes(&mut self, n: u16) -> io::Result<Vec<AttributeInfo>> {
         let mut res = Vec::new();
         for i in 0..n {
@@ -402,7 +830,57 @@ impl ClassFileReader {
                 })? {
                 ConstantPoolInfo::Utf8 { string } => match string.as_str() {
                     "Code" => self.read_code_attrib()?,
+                    "ConstantValue" => {
+                        let constantvalue_index = self.buf.read_u16::<BigEndian>()?;
+                        match self.constant_pool.get(constantvalue_index as usize) {
+                            Some(
+                                ConstantPoolInfo::Integer { .. }
+                                | ConstantPoolInfo::Float { .. }
+                                | ConstantPoolInfo::Long { .. }
+                                | ConstantPoolInfo::Double { .. }
+                                | ConstantPoolInfo::String { .. },
+                            ) => Attribute::ConstantValue {
+                                constantvalue_index,
+                            },
+                            other => {
+                                return Err(self.report_error(&format!(
+                                    "Expected loadable constant for ConstantValue, received '{:?}' at index {}",
+                                    other, constantvalue_index
+                                )))
+                            }
+                        }
+                    }
                     "LineNumberTable" => self.read_line_number_table_attrib()?,
+                    "StackMapTable" => self.read_stack_map_table_attrib()?,
+                    "MethodParameters" => self.read_method_parameters_attrib()?,
+                    "Module" => self.read_module_attrib()?,
+                    "ModulePackages" => {
+                        let package_count = self.buf.read_u16::<BigEndian>()?;
+                        let package_index = self.read_u16_list(package_count)?;
+                        Attribute::ModulePackages {
+                            package_count,
+                            package_index,
+                        }
+                    }
+                    "ModuleMainClass" => {
+                        let main_class_index = self.buf.read_u16::<BigEndian>()?;
+                        Attribute::ModuleMainClass { main_class_index }
+                    }
+                    "PermittedSubclasses" => {
+                        let number_of_classes = self.buf.read_u16::<BigEndian>()?;
+                        let classes = self.read_u16_list(number_of_classes)?;
+                        Attribute::PermittedSubclasses {
+                            number_of_classes,
+                            classes,
+                        }
+                    }
+                    "Synthetic" if attribute_length != 0 => {
+                        return Err(self.report_error(&format!(
+                            "ClassFormatError: Synthetic attribute has length {}, expected 0",
+                            attribute_length
+                        )))
+                    }
+                    "Synthetic" => Attribute::Synthetic {},
                     "SourceFile" => {
                         let sourcefile_index = self.buf.read_u16::<BigEndian>()?;
                         Attribute::SourceFile { sourcefile_index }
@@ -435,8 +913,11 @@ impl ClassFileReader {
                 .ok_or_else(|| {
                     self.report_error("Expected field access flags, got invalid flag")
                 })?;
+            self.check_field_flags(&access_flags)?;
             let name_index = self.buf.read_u16::<BigEndian>()?;
+            self.expect_utf8(name_index, "name_index")?;
             let descriptor_index = self.buf.read_u16::<BigEndian>()?;
+            self.expect_utf8(descriptor_index, "descriptor_index")?;
             let attributes_count = self.buf.read_u16::<BigEndian>()?;
             let attributes = self.read_attributes(attributes_count)?;
             fields.push(FieldInfo {
@@ -451,22 +932,49 @@ impl ClassFileReader {
         Ok(fields)
     }
 
-    fn read_interfaces(&mut self, n: u16) -> io::Result<Vec<ConstantPoolInfo>> {
+    /// Each interface is a u16 index of a CONSTANT_Class_info in the constant pool.
+    fn read_interfaces(&mut self, n: u16) -> io::Result<Vec<u16>> {
         let mut interfaces = Vec::new();
         for i in 0..n {
-            let tag = self.buf.read_u8()?;
-            if tag != ConstantPoolTag::Class.into() {
-                return Err(self.report_error(&format!(
-                    "Expected CONSTANT_Class_info tag, received tag '{}'",
-                    tag
-                )));
-            } else {
-                interfaces.push(self.constant_tag(tag)?);
-            }
+            let index = self.buf.read_u16::<BigEndian>()?;
+            self.expect_class(index, "interface")?;
+            interfaces.push(index);
         }
         Ok(interfaces)
     }
 
+    fn expect_class(&self, index: u16, what: &str) -> io::Result<()> {
+        match self.constant_pool.get(index as usize) {
+            Some(ConstantPoolInfo::Class { .. }) => Ok(()),
+            other => Err(self.report_error(&format!(
+                "Expected CONSTANT_Class_info for {}, received '{:?}' at index {}",
+                what, other, index
+            ))),
+        }
+    }
+
+    fn expect_utf8(&self, index: u16, what: &str) -> io::Result<()> {
+        match self.constant_pool.get(index as usize) {
+            Some(ConstantPoolInfo::Utf8 { .. }) => Ok(()),
+            other => Err(self.report_error(&format!(
+                "ClassFormatError: Expected CONSTANT_Utf8_info for {}, received '{:?}' at index {}",
+                what, other, index
+            ))),
+        }
+    }
+
+    fn class_name(&self, index: u16) -> Option<&str> {
+        match self.constant_pool.get(index as usize)? {
+            ConstantPoolInfo::Class { name_index } => {
+                match self.constant_pool.get(*name_index as usize)? {
+                    ConstantPoolInfo::Utf8 { string } => Some(string),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
     fn constant_tag(&mut self, tag: u8) -> io::Result<ConstantPoolInfo> {
         let mut data: Vec<u8> = Vec::new();
         use ConstantPoolInfo as CInfo;
@@ -475,8 +983,7 @@ impl ClassFileReader {
             match parsed_tag {
                 C::Utf8 => {
                     let length = self.buf.read_u16::<BigEndian>()?;
-                    let mut bytes = vec![0u8; length as usize];
-                    self.buf.read_exact(&mut bytes)?;
+                    let bytes = self.read_bytes(length as usize)?;
                     match String::from_utf8(bytes) {
                         Ok(str) => Ok(CInfo::Utf8 { string: str }),
                         Err(e) => Err(self.report_error("UTF-8 String was not in correct format.")),
@@ -580,14 +1087,890 @@ impl ClassFileReader {
         }
     }
 
+    /// Reads exactly `length` bytes, refusing lengths larger than the rest of the input
+    /// so a corrupt length field can't trigger a huge allocation.
+    fn read_bytes(&mut self, length: usize) -> io::Result<Vec<u8>> {
+        if length > self.remaining().len() {
+            return Err(self.report_error(&format!(
+                "Expected {} bytes, only {} remaining",
+                length,
+                self.remaining().len()
+            )));
+        }
+        let mut bytes = vec![0u8; length];
+        self.buf.read_exact(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    fn remaining(&self) -> &[u8] {
+        let data = self.buf.get_ref();
+        let pos = (self.buf.position() as usize).min(data.len());
+        &data[pos..]
+    }
+
+    /// Checks that every reference inside the constant pool points at an entry of
+    /// the kind the spec requires, so later lookups can't hit a wrong tag.
+    fn validate_constant_pool(&self) -> io::Result<()> {
+        use ConstantPoolInfo as CInfo;
+        let is_utf8 = |e: &CInfo| matches!(e, CInfo::Utf8 { .. });
+        let is_class = |e: &CInfo| matches!(e, CInfo::Class { .. });
+        let is_name_and_type = |e: &CInfo| matches!(e, CInfo::NameAndType { .. });
+        for (i, entry) in self.constant_pool.iter().enumerate() {
+            match *entry {
+                CInfo::Class { name_index }
+                | CInfo::Module { name_index }
+                | CInfo::Package { name_index } => {
+                    self.expect_entry(i, name_index, "Utf8", is_utf8)?
+                }
+                CInfo::String { string_index } => {
+                    self.expect_entry(i, string_index, "Utf8", is_utf8)?
+                }
+                CInfo::MethodType { descriptor_index } => {
+                    self.expect_entry(i, descriptor_index, "Utf8", is_utf8)?
+                }
+                CInfo::FieldRef {
+                    class_index,
+                    name_and_type_index,
+                }
+                | CInfo::MethodRef {
+                    class_index,
+                    name_and_type_index,
+                }
+                | CInfo::InterfaceMethodRef {
+                    class_index,
+                    name_and_type_index,
+                } => {
+                    self.expect_entry(i, class_index, "Class", is_class)?;
+                    self.expect_entry(i, name_and_type_index, "NameAndType", is_name_and_type)?;
+                }
+                CInfo::NameAndType {
+                    name_index,
+                    descriptor_index,
+                } => {
+                    self.expect_entry(i, name_index, "Utf8", is_utf8)?;
+                    self.expect_entry(i, descriptor_index, "Utf8", is_utf8)?;
+                }
+                CInfo::Dynamic {
+                    name_and_type_index,
+                    ..
+                }
+                | CInfo::InvokeDynamic {
+                    name_and_type_index,
+                    ..
+                } => self.expect_entry(i, name_and_type_index, "NameAndType", is_name_and_type)?,
+                CInfo::MethodHandle {
+                    reference_kind,
+                    reference_index,
+                } => match reference_kind {
+                    1..=4 => self.expect_entry(i, reference_index, "FieldRef", |e| {
+                        matches!(e, CInfo::FieldRef { .. })
+                    })?,
+                    5 | 8 => self.expect_entry(i, reference_index, "MethodRef", |e| {
+                        matches!(e, CInfo::MethodRef { .. })
+                    })?,
+                    6 | 7 => self.expect_entry(
+                        i,
+                        reference_index,
+                        "MethodRef or InterfaceMethodRef",
+                        |e| {
+                            matches!(
+                                e,
+                                CInfo::MethodRef { .. } | CInfo::InterfaceMethodRef { .. }
+                            )
+                        },
+                    )?,
+                    9 => self.expect_entry(i, reference_index, "InterfaceMethodRef", |e| {
+                        matches!(e, CInfo::InterfaceMethodRef { .. })
+                    })?,
+                    other => {
+                        return Err(self.report_error(&format!(
+                            "Constant pool entry #{} has invalid reference kind '{}'",
+                            i, other
+                        )))
+                    }
+                },
+                CInfo::Utf8 { .. }
+                | CInfo::Integer { .. }
+                | CInfo::Float { .. }
+                | CInfo::Long { .. }
+                | CInfo::Double { .. }
+                | CInfo::Unusable => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn expect_entry(
+        &self,
+        owner: usize,
+        index: u16,
+        expected: &str,
+        is_expected: impl Fn(&ConstantPoolInfo) -> bool,
+    ) -> io::Result<()> {
+        match self.constant_pool.get(index as usize) {
+            Some(entry) if is_expected(entry) => Ok(()),
+            other => Err(self.report_error(&format!(
+                "Constant pool entry #{} expected {} at index {}, received '{:?}'",
+                owner, expected, index, other
+            ))),
+        }
+    }
+
     fn report_error(&self, message: &str) -> io::Error {
-        println!("[ERROR]: {}", message);
-        println!("BYTES: {:?}", self.buf.buffer());
+        // only a short prefix, the rest of the input may be arbitrarily large
+        const SHOWN: usize = 16;
+        let remaining = self.remaining();
+        let next = &remaining[..remaining.len().min(SHOWN)];
         io::Error::new(
             io::ErrorKind::InvalidData,
-            "Bytecode file was not in the correct format!",
+            format!(
+                "{} (at byte {}, next bytes {:02X?}{})",
+                message,
+                self.buf.position(),
+                next,
+                if remaining.len() > SHOWN { "..." } else { "" }
+            ),
+        )
+    }
+}
+
+impl FieldInfo {
+    pub fn is_synthetic(&self) -> bool {
+        self.access_flags.contains(FieldAccessFlags::Synthetic)
+            || has_synthetic_attribute(&self.attributes)
+    }
+}
+
+impl MethodInfo {
+    /// Bridge methods and other compiler-generated methods.
+    pub fn is_synthetic(&self) -> bool {
+        self.access_flags.contains(MethodAccessFlags::Synthetic)
+            || has_synthetic_attribute(&self.attributes)
+    }
+}
+
+impl ClassFile {
+    pub fn name(&self) -> Option<&str> {
+        self.get_class_name(self.this_class)
+    }
+
+    /// `None` for a class without a superclass (only `java/lang/Object`).
+    pub fn super_class_name(&self) -> Option<&str> {
+        self.get_class_name(self.super_class)
+    }
+
+    pub fn interface_names(&self) -> Vec<&str> {
+        self.interfaces
+            .iter()
+            .filter_map(|&index| self.get_class_name(index))
+            .collect()
+    }
+
+    pub fn is_interface(&self) -> bool {
+        self.access_flags.contains(ClassAccessFlags::Interface)
+    }
+
+    pub fn is_abstract(&self) -> bool {
+        self.access_flags.contains(ClassAccessFlags::Abstract)
+    }
+
+    pub fn is_enum(&self) -> bool {
+        self.access_flags.contains(ClassAccessFlags::Enum)
+    }
+
+    /// Compiler-generated, marked either by the access flag or a `Synthetic` attribute.
+    pub fn is_synthetic(&self) -> bool {
+        self.access_flags.contains(ClassAccessFlags::Synthetic)
+            || has_synthetic_attribute(&self.attributes)
+    }
+
+    /// The classes named by a `PermittedSubclasses` attribute, or `None` if the class isn't sealed.
+    pub fn permitted_subclasses(&self) -> Option<Vec<&str>> {
+        self.attributes
+            .iter()
+            .find_map(|attribute| match &attribute.info {
+                Attribute::PermittedSubclasses { classes, .. } => Some(
+                    classes
+                        .iter()
+                        .filter_map(|&index| self.get_class_name(index))
+                        .collect(),
+                ),
+                _ => None,
+            })
+    }
+
+    /// Modules named by the `requires` table of a module-info's `Module` attribute.
+    pub fn module_requires(&self) -> Vec<&str> {
+        self.attributes
+            .iter()
+            .find_map(|attribute| match &attribute.info {
+                Attribute::Module { requires, .. } => Some(
+                    requires
+                        .iter()
+                        .filter_map(|entry| {
+                            match self.constant_pool.get(entry.requires_index as usize)? {
+                                ConstantPoolInfo::Module { name_index } => {
+                                    self.get_utf8(*name_index)
+                                }
+                                _ => None,
+                            }
+                        })
+                        .collect(),
+                ),
+                _ => None,
+            })
+            .unwrap_or_default()
+    }
+
+    /// Parameter names recorded by `javac -parameters` for the method with the
+    /// given name and descriptor. Unnamed parameters (`name_index` 0) are `None`;
+    /// returns `None` if there's no such method or it has no `MethodParameters`.
+    pub fn parameter_names(&self, name: &str, descriptor: &str) -> Option<Vec<Option<&str>>> {
+        let method = self.methods.iter().find(|method| {
+            self.get_utf8(method.name_index) == Some(name)
+                && self.get_utf8(method.descriptor_index) == Some(descriptor)
+        })?;
+        method
+            .attributes
+            .iter()
+            .find_map(|attribute| match &attribute.info {
+                Attribute::MethodParameters { parameters, .. } => Some(
+                    parameters
+                        .iter()
+                        .map(|parameter| match parameter.name_index {
+                            0 => None,
+                            index => self.get_utf8(index),
+                        })
+                        .collect(),
+                ),
+                _ => None,
+            })
+    }
+
+    /// Lists every constant pool entry with its tag, raw operands, and the
+    /// values its references resolve to, e.g. `#1 = Methodref #2.#3 // ...`.
+    pub fn dump_constant_pool(&self) -> String {
+        let mut out = String::new();
+        self.write_constant_pool(&mut out)
+            .expect("writing to a String can't fail");
+        out
+    }
+
+    fn write_constant_pool(&self, out: &mut String) -> fmt::Result {
+        for (i, entry) in self.constant_pool.iter().enumerate().skip(1) {
+            if let ConstantPoolInfo::Unusable = entry {
+                continue;
+            }
+            let index = format!("#{}", i);
+            match self.constant_operands(entry) {
+                Some(operands) => writeln!(
+                    out,
+                    "{:>6} = {:<18} {:<14} // {}",
+                    index,
+                    constant_tag_name(entry),
+                    operands,
+                    self.describe_constant(i as u16)
+                )?,
+                None => writeln!(
+                    out,
+                    "{:>6} = {:<18} {}",
+                    index,
+                    constant_tag_name(entry),
+                    self.describe_constant(i as u16)
+                )?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Renders a javap-like dump of the whole class: header, constant pool with
+    /// resolved cross-references, fields, and methods with disassembled code.
+    pub fn pretty_print(&self) -> String {
+        let mut out = String::new();
+        self.write_pretty(&mut out)
+            .expect("writing to a String can't fail");
+        out
+    }
+
+    fn write_pretty(&self, out: &mut String) -> fmt::Result {
+        writeln!(out, "class {}", self.describe_constant(self.this_class))?;
+        writeln!(out, "  minor version: {}", self.minor_version)?;
+        writeln!(out, "  major version: {}", self.major_version)?;
+        writeln!(
+            out,
+            "  flags: (0x{:04x}) {}",
+            self.access_flags.bits(),
+            flag_names(self.access_flags.iter_names())
+        )?;
+        writeln!(
+            out,
+            "  this_class: {:<27} // {}",
+            format!("#{}", self.this_class),
+            self.describe_constant(self.this_class)
+        )?;
+        if self.super_class == 0 {
+            writeln!(out, "  super_class: #0")?;
+        } else {
+            writeln!(
+                out,
+                "  super_class: {:<26} // {}",
+                format!("#{}", self.super_class),
+                self.describe_constant(self.super_class)
+            )?;
+        }
+        writeln!(
+            out,
+            "  interfaces: {}, fields: {}, methods: {}, attributes: {}",
+            self.interfaces_count, self.fields_count, self.methods_count, self.attributes_count
+        )?;
+        for &interface in &self.interfaces {
+            writeln!(out, "    {}", self.describe_constant(interface))?;
+        }
+
+        writeln!(out, "Constant pool:")?;
+        self.write_constant_pool(out)?;
+
+        writeln!(out, "{{")?;
+        for field in &self.fields {
+            writeln!(
+                out,
+                "  {}",
+                self.get_utf8(field.name_index).unwrap_or("<invalid>")
+            )?;
+            writeln!(
+                out,
+                "    descriptor: {}",
+                self.get_utf8(field.descriptor_index).unwrap_or("<invalid>")
+            )?;
+            writeln!(
+                out,
+                "    flags: (0x{:04x}) {}",
+                field.access_flags.bits(),
+                flag_names(field.access_flags.iter_names())
+            )?;
+            for attribute in &field.attributes {
+                self.write_attribute(out, attribute, "    ")?;
+            }
+            writeln!(out)?;
+        }
+        for method in &self.methods {
+            writeln!(
+                out,
+                "  {}",
+                self.get_utf8(method.name_index).unwrap_or("<invalid>")
+            )?;
+            writeln!(
+                out,
+                "    descriptor: {}",
+                self.get_utf8(method.descriptor_index)
+                    .unwrap_or("<invalid>")
+            )?;
+            writeln!(
+                out,
+                "    flags: (0x{:04x}) {}",
+                method.access_flags.bits(),
+                flag_names(method.access_flags.iter_names())
+            )?;
+            for attribute in &method.attributes {
+                self.write_attribute(out, attribute, "    ")?;
+            }
+            writeln!(out)?;
+        }
+        writeln!(out, "}}")?;
+        for attribute in &self.attributes {
+            self.write_attribute(out, attribute, "")?;
+        }
+        Ok(())
+    }
+
+    fn write_attribute(
+        &self,
+        out: &mut String,
+        attribute: &AttributeInfo,
+        indent: &str,
+    ) -> fmt::Result {
+        match &attribute.info {
+            Attribute::Code {
+                max_stack,
+                max_locals,
+                code,
+                exception_table,
+                attributes,
+                ..
+            } => {
+                writeln!(out, "{}Code:", indent)?;
+                writeln!(
+                    out,
+                    "{}  stack={}, locals={}",
+                    indent, max_stack, max_locals
+                )?;
+                self.write_code(out, code, indent)?;
+                if !exception_table.is_empty() {
+                    writeln!(out, "{}  Exception table:", indent)?;
+                    writeln!(out, "{}     from    to  target type", indent)?;
+                    for entry in exception_table {
+                        let catch_type = if entry.catch_type == 0 {
+                            "any".to_string()
+                        } else {
+                            format!("Class {}", self.describe_constant(entry.catch_type))
+                        };
+                        writeln!(
+                            out,
+                            "{}    {:>5} {:>5} {:>5}   {}",
+                            indent, entry.start_pc, entry.end_pc, entry.handler_pc, catch_type
+                        )?;
+                    }
+                }
+                let nested = format!("{}  ", indent);
+                for attribute in attributes {
+                    self.write_attribute(out, attribute, &nested)?;
+                }
+            }
+            Attribute::LineNumberTable {
+                line_number_table, ..
+            } => {
+                writeln!(out, "{}LineNumberTable:", indent)?;
+                for entry in line_number_table {
+                    writeln!(
+                        out,
+                        "{}  line {}: {}",
+                        indent, entry.line_number, entry.start_pc
+                    )?;
+                }
+            }
+            Attribute::ConstantValue {
+                constantvalue_index,
+            } => {
+                let kind = match self.constant_pool.get(*constantvalue_index as usize) {
+                    Some(ConstantPoolInfo::Integer { .. }) => "int",
+                    Some(ConstantPoolInfo::Float { .. }) => "float",
+                    Some(ConstantPoolInfo::Long { .. }) => "long",
+                    Some(ConstantPoolInfo::Double { .. }) => "double",
+                    _ => "String",
+                };
+                writeln!(
+                    out,
+                    "{}ConstantValue: {} {}",
+                    indent,
+                    kind,
+                    self.describe_constant(*constantvalue_index)
+                )?
+            }
+            Attribute::SourceFile { sourcefile_index } => writeln!(
+                out,
+                "{}SourceFile: \"{}\"",
+                indent,
+                self.get_utf8(*sourcefile_index).unwrap_or("<invalid>")
+            )?,
+            _ => writeln!(
+                out,
+                "{}{}",
+                indent,
+                self.get_utf8(attribute.attribute_name_index)
+                    .unwrap_or("<invalid>")
+            )?,
+        }
+        Ok(())
+    }
+
+    fn write_code(&self, out: &mut String, code: &[u8], indent: &str) -> fmt::Result {
+        let mut pc = 0;
+        while pc < code.len() {
+            match self.disassemble_instruction(code, pc, indent) {
+                Ok((text, length)) => {
+                    writeln!(out, "{}  {:>6}: {}", indent, pc, text)?;
+                    pc += length;
+                }
+                Err(e) => {
+                    writeln!(out, "{}  {:>6}: <malformed: {}>", indent, pc, e)?;
+                    break;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Decodes the instruction at `pc`, returning its text and its length in bytes.
+    fn disassemble_instruction(
+        &self,
+        code: &[u8],
+        pc: usize,
+        indent: &str,
+    ) -> io::Result<(String, usize)> {
+        use Opcode as O;
+        let op = Opcode::from_byte(code[pc])?;
+        let name = op.name();
+        let mut cur = Cursor::new(code);
+        cur.set_position(pc as u64 + 1);
+        let text = match op {
+            O::BIPush => format!("{:<13} {}", name, cur.read_i8()?),
+            O::SIPush => format!("{:<13} {}", name, cur.read_i16::<BigEndian>()?),
+            O::Ldc => self.with_constant(name, cur.read_u8()? as u16),
+            O::LdcW
+            | O::Ldc2W
+            | O::GetStatic
+            | O::PutStatic
+            | O::GetField
+            | O::PutField
+            | O::InvokeVirtual
+            | O::InvokeSpecial
+            | O::InvokeStatic
+            | O::New
+            | O::ANewArray
+            | O::CheckCast
+            | O::InstanceOf => self.with_constant(name, cur.read_u16::<BigEndian>()?),
+            O::InvokeInterface => {
+                let index = cur.read_u16::<BigEndian>()?;
+                let count = cur.read_u8()?;
+                cur.read_u8()?;
+                format!(
+                    "{:<13} {:<18} // {}",
+                    name,
+                    format!("#{},  {}", index, count),
+                    self.describe_operand(index)
+                )
+            }
+            O::InvokeDynamic => {
+                let index = cur.read_u16::<BigEndian>()?;
+                cur.read_u16::<BigEndian>()?;
+                self.with_constant(name, index)
+            }
+            O::MultiANewArray => {
+                let index = cur.read_u16::<BigEndian>()?;
+                let dimensions = cur.read_u8()?;
+                format!(
+                    "{:<13} {:<18} // {}",
+                    name,
+                    format!("#{},  {}", index, dimensions),
+                    self.describe_operand(index)
+                )
+            }
+            O::ILoad
+            | O::LLoad
+            | O::FLoad
+            | O::DLoad
+            | O::ALoad
+            | O::IStore
+            | O::LStore
+            | O::FStore
+            | O::DStore
+            | O::AStore
+            | O::Ret => format!("{:<13} {}", name, cur.read_u8()?),
+            O::IInc => format!("{:<13} {}, {}", name, cur.read_u8()?, cur.read_i8()?),
+            O::IfEq
+            | O::IfNe
+            | O::IfLt
+            | O::IfGe
+            | O::IfGt
+            | O::IfLe
+            | O::IfICmpEq
+            | O::IfICmpNe
+            | O::IfICmpLt
+            | O::IfICmpGe
+            | O::IfICmpGt
+            | O::IfICmpLe
+            | O::IfACmpEq
+            | O::IfACmpNe
+            | O::Goto
+            | O::Jsr
+            | O::IfNull
+            | O::IfNonNull => {
+                let offset = cur.read_i16::<BigEndian>()?;
+                format!("{:<13} {}", name, pc as i64 + offset as i64)
+            }
+            O::GotoW | O::JsrW => {
+                let offset = cur.read_i32::<BigEndian>()?;
+                format!("{:<13} {}", name, pc as i64 + offset as i64)
+            }
+            O::NewArray => {
+                let atype = cur.read_u8()?;
+                let element = match atype {
+                    4 => "boolean",
+                    5 => "char",
+                    6 => "float",
+                    7 => "double",
+                    8 => "byte",
+                    9 => "short",
+                    10 => "int",
+                    11 => "long",
+                    _ => {
+                        return Err(io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            format!("Invalid newarray type '{}'", atype),
+                        ))
+                    }
+                };
+                format!("{:<13} {}", name, element)
+            }
+            O::TableSwitch | O::LookupSwitch => {
+                let (table, length) = SwitchTable::read(code, pc)?;
+                cur.set_position((pc + length) as u64);
+                let target = |offset: i32| pc as i64 + offset as i64;
+                let (header, default, entries) = match table {
+                    SwitchTable::Table {
+                        default,
+                        low,
+                        high,
+                        offsets,
+                    } => (
+                        format!("{} to {}", low, high),
+                        default,
+                        (low as i64..=high as i64).zip(offsets).collect::<Vec<_>>(),
+                    ),
+                    SwitchTable::Lookup { default, pairs } => (
+                        pairs.len().to_string(),
+                        default,
+                        pairs
+                            .into_iter()
+                            .map(|(key, offset)| (key as i64, offset))
+                            .collect(),
+                    ),
+                };
+                let mut text = format!("{:<13} {{ // {}\n", name, header);
+                for (key, offset) in entries {
+                    text += &format!("{}  {:>20}: {}\n", indent, key, target(offset));
+                }
+                text += &format!("{}  {:>20}: {}\n", indent, "default", target(default));
+                text + &format!("{}          }}", indent)
+            }
+            O::Wide => {
+                let inner = Opcode::from_byte(cur.read_u8()?)?;
+                let index = cur.read_u16::<BigEndian>()?;
+                match inner {
+                    O::IInc => format!(
+                        "{} {:<8} {}, {}",
+                        name,
+                        inner.name(),
+                        index,
+                        cur.read_i16::<BigEndian>()?
+                    ),
+                    O::ILoad
+                    | O::LLoad
+                    | O::FLoad
+                    | O::DLoad
+                    | O::ALoad
+                    | O::IStore
+                    | O::LStore
+                    | O::FStore
+                    | O::DStore
+                    | O::AStore
+                    | O::Ret => format!("{} {:<8} {}", name, inner.name(), index),
+                    other => {
+                        return Err(io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            format!("Opcode '{}' can't be widened", other.name()),
+                        ))
+                    }
+                }
+            }
+            _ => name.to_string(),
+        };
+        Ok((text, cur.position() as usize - pc))
+    }
+
+    fn with_constant(&self, name: &str, index: u16) -> String {
+        format!(
+            "{:<13} {:<18} // {}",
+            name,
+            format!("#{}", index),
+            self.describe_operand(index)
         )
     }
+
+    /// Describes a constant referenced from code, labelled the way javap does.
+    fn describe_operand(&self, index: u16) -> String {
+        use ConstantPoolInfo as CInfo;
+        let label = match self.constant_pool.get(index as usize) {
+            Some(CInfo::FieldRef { .. }) => "Field",
+            Some(CInfo::MethodRef { .. }) => "Method",
+            Some(CInfo::InterfaceMethodRef { .. }) => "InterfaceMethod",
+            Some(CInfo::Class { .. }) => "class",
+            Some(CInfo::Integer { .. }) => "int",
+            Some(CInfo::Float { .. }) => "float",
+            Some(CInfo::Long { .. }) => "long",
+            Some(CInfo::Double { .. }) => "double",
+            Some(entry) => constant_tag_name(entry),
+            None => "",
+        };
+        format!("{} {}", label, self.describe_constant(index))
+    }
+
+    /// Raw index operands of a reference entry, e.g. `#2.#3`; `None` for value entries.
+    fn constant_operands(&self, entry: &ConstantPoolInfo) -> Option<String> {
+        use ConstantPoolInfo as CInfo;
+        Some(match entry {
+            CInfo::Class { name_index }
+            | CInfo::Module { name_index }
+            | CInfo::Package { name_index } => format!("#{}", name_index),
+            CInfo::String { string_index } => format!("#{}", string_index),
+            CInfo::MethodType { descriptor_index } => format!("#{}", descriptor_index),
+            CInfo::FieldRef {
+                class_index,
+                name_and_type_index,
+            }
+            | CInfo::MethodRef {
+                class_index,
+                name_and_type_index,
+            }
+            | CInfo::InterfaceMethodRef {
+                class_index,
+                name_and_type_index,
+            } => format!("#{}.#{}", class_index, name_and_type_index),
+            CInfo::NameAndType {
+                name_index,
+                descriptor_index,
+            } => format!("#{}:#{}", name_index, descriptor_index),
+            CInfo::MethodHandle {
+                reference_kind,
+                reference_index,
+            } => format!("{}:#{}", reference_kind, reference_index),
+            CInfo::Dynamic {
+                bootstrap_method_attr_index,
+                name_and_type_index,
+            }
+            | CInfo::InvokeDynamic {
+                bootstrap_method_attr_index,
+                name_and_type_index,
+            } => format!("#{}:#{}", bootstrap_method_attr_index, name_and_type_index),
+            CInfo::Utf8 { .. }
+            | CInfo::Integer { .. }
+            | CInfo::Float { .. }
+            | CInfo::Long { .. }
+            | CInfo::Double { .. }
+            | CInfo::Unusable => return None,
+        })
+    }
+
+    /// Resolves a constant pool entry to readable text, following references.
+    fn describe_constant(&self, index: u16) -> String {
+        use ConstantPoolInfo as CInfo;
+        let entry = match self.constant_pool.get(index as usize) {
+            Some(entry) => entry,
+            None => return format!("<invalid #{}>", index),
+        };
+        match entry {
+            CInfo::Utf8 { string } => string.clone(),
+            CInfo::Integer { bytes } => bytes.to_string(),
+            CInfo::Float { bytes } => format!("{}f", bytes),
+            CInfo::Long { bytes } => format!("{}l", bytes),
+            CInfo::Double { bytes } => format!("{}d", bytes),
+            CInfo::Class { name_index }
+            | CInfo::Module { name_index }
+            | CInfo::Package { name_index } => self.describe_constant(*name_index),
+            CInfo::String { string_index } => self.describe_constant(*string_index),
+            CInfo::MethodType { descriptor_index } => self.describe_constant(*descriptor_index),
+            CInfo::FieldRef {
+                class_index,
+                name_and_type_index,
+            }
+            | CInfo::MethodRef {
+                class_index,
+                name_and_type_index,
+            }
+            | CInfo::InterfaceMethodRef {
+                class_index,
+                name_and_type_index,
+            } => format!(
+                "{}.{}",
+                self.describe_constant(*class_index),
+                self.describe_constant(*name_and_type_index)
+            ),
+            CInfo::NameAndType {
+                name_index,
+                descriptor_index,
+            } => {
+                let name = self.describe_constant(*name_index);
+                let name = if name.starts_with('<') {
+                    format!("\"{}\"", name)
+                } else {
+                    name
+                };
+                format!("{}:{}", name, self.describe_constant(*descriptor_index))
+            }
+            CInfo::MethodHandle {
+                reference_kind,
+                reference_index,
+            } => {
+                let kind = match reference_kind {
+                    1 => "REF_getField",
+                    2 => "REF_getStatic",
+                    3 => "REF_putField",
+                    4 => "REF_putStatic",
+                    5 => "REF_invokeVirtual",
+                    6 => "REF_invokeStatic",
+                    7 => "REF_invokeSpecial",
+                    8 => "REF_newInvokeSpecial",
+                    9 => "REF_invokeInterface",
+                    _ => "REF_invalid",
+                };
+                format!("{} {}", kind, self.describe_constant(*reference_index))
+            }
+            CInfo::Dynamic {
+                bootstrap_method_attr_index,
+                name_and_type_index,
+            }
+            | CInfo::InvokeDynamic {
+                bootstrap_method_attr_index,
+                name_and_type_index,
+            } => format!(
+                "#{}:{}",
+                bootstrap_method_attr_index,
+                self.describe_constant(*name_and_type_index)
+            ),
+            CInfo::Unusable => format!("<unusable #{}>", index),
+        }
+    }
+
+    fn get_utf8(&self, index: u16) -> Option<&str> {
+        match self.constant_pool.get(index as usize)? {
+            ConstantPoolInfo::Utf8 { string } => Some(string),
+            _ => None,
+        }
+    }
+
+    fn get_class_name(&self, index: u16) -> Option<&str> {
+        match self.constant_pool.get(index as usize)? {
+            ConstantPoolInfo::Class { name_index } => self.get_utf8(*name_index),
+            _ => None,
+        }
+    }
+}
+
+fn has_synthetic_attribute(attributes: &[AttributeInfo]) -> bool {
+    attributes
+        .iter()
+        .any(|attribute| matches!(attribute.info, Attribute::Synthetic {}))
+}
+
+fn constant_tag_name(entry: &ConstantPoolInfo) -> &'static str {
+    use ConstantPoolInfo as CInfo;
+    match entry {
+        CInfo::Utf8 { .. } => "Utf8",
+        CInfo::Integer { .. } => "Integer",
+        CInfo::Float { .. } => "Float",
+        CInfo::Long { .. } => "Long",
+        CInfo::Double { .. } => "Double",
+        CInfo::Class { .. } => "Class",
+        CInfo::String { .. } => "String",
+        CInfo::FieldRef { .. } => "Fieldref",
+        CInfo::MethodRef { .. } => "Methodref",
+        CInfo::InterfaceMethodRef { .. } => "InterfaceMethodref",
+        CInfo::NameAndType { .. } => "NameAndType",
+        CInfo::MethodHandle { .. } => "MethodHandle",
+        CInfo::MethodType { .. } => "MethodType",
+        CInfo::Dynamic { .. } => "Dynamic",
+        CInfo::InvokeDynamic { .. } => "InvokeDynamic",
+        CInfo::Module { .. } => "Module",
+        CInfo::Package { .. } => "Package",
+        CInfo::Unusable => "Unusable",
+    }
+}
+
+fn flag_names<T>(names: impl Iterator<Item = (&'static str, T)>) -> String {
+    names
+        .map(|(name, _)| format!("ACC_{}", name.to_uppercase()))
+        .collect::<Vec<_>>()
+        .join(", ")
 }
 
 #[derive(IntoPrimitive, TryFromPrimitive)]
@@ -611,3 +1994,462 @@ enum ConstantPoolTag {
     Module = 19,
     Package = 20,
 }
+
+#[cfg(test)]
+mod tests {
+    //! Fixtures live in `tests/fixtures` and are built from the `.java` file
+    //! next to each one with `javac -parameters`.
+
+    use super::*;
+
+    fn read_fixture(bytes: &[u8]) -> io::Result<ClassFile> {
+        ClassFileReader::from_bytes(bytes.to_vec()).read()
+    }
+
+    /// Offset of `access_flags`, just past the constant pool.
+    fn pool_end(bytes: &[u8]) -> usize {
+        let count = u16::from_be_bytes([bytes[8], bytes[9]]);
+        let (mut at, mut index) = (10, 1);
+        while index < count {
+            let tag = ConstantPoolTag::try_from(bytes[at]).unwrap();
+            at += 1 + match tag {
+                ConstantPoolTag::Utf8 => {
+                    2 + u16::from_be_bytes([bytes[at + 1], bytes[at + 2]]) as usize
+                }
+                ConstantPoolTag::Long | ConstantPoolTag::Double => 8,
+                ConstantPoolTag::Integer
+                | ConstantPoolTag::Float
+                | ConstantPoolTag::FieldRef
+                | ConstantPoolTag::MethodRef
+                | ConstantPoolTag::InterfaceMethodRef
+                | ConstantPoolTag::NameAndType
+                | ConstantPoolTag::Dynamic
+                | ConstantPoolTag::InvokeDynamic => 4,
+                ConstantPoolTag::MethodHandle => 3,
+                _ => 2,
+            };
+            index += match tag {
+                ConstantPoolTag::Long | ConstantPoolTag::Double => 2,
+                _ => 1,
+            };
+        }
+        at
+    }
+
+    fn patch_u16(bytes: &[u8], at: usize, value: u16) -> Vec<u8> {
+        let mut bytes = bytes.to_vec();
+        bytes[at..at + 2].copy_from_slice(&value.to_be_bytes());
+        bytes
+    }
+
+    const TWO: &[u8] = include_bytes!("../tests/fixtures/Two.class");
+
+    #[test]
+    fn method_parameter_names() {
+        let class = read_fixture(TWO).unwrap();
+        assert_eq!(
+            class.parameter_names("sum", "(ILjava/lang/String;)I"),
+            Some(vec![Some("x"), Some("label")])
+        );
+        assert_eq!(class.parameter_names("run", "(I)V"), None);
+    }
+
+    #[test]
+    fn module_info_requires() {
+        let class =
+            read_fixture(include_bytes!("../tests/fixtures/module/module-info.class")).unwrap();
+        assert_eq!(class.name(), Some("module-info"));
+        assert_eq!(class.module_requires(), vec!["java.base", "java.logging"]);
+        assert!(read_fixture(TWO).unwrap().module_requires().is_empty());
+    }
+
+    const PLAIN: &[u8] = include_bytes!("../tests/fixtures/Plain.class");
+    const ABS: &[u8] = include_bytes!("../tests/fixtures/Abs.class");
+
+    #[test]
+    fn reserved_opcode_in_code_is_rejected() {
+        // Plain.<init>: aload_0, invokespecial #1, return
+        let body = [0x2a, 0xb7, 0x00, 0x01, 0xb1];
+        let at = PLAIN
+            .windows(body.len())
+            .position(|window| window == body)
+            .unwrap();
+        let mut bytes = PLAIN.to_vec();
+        bytes[at + 4] = 0xfe; // impdep1
+        assert!(read_fixture(PLAIN).is_ok());
+        assert!(read_fixture(&bytes).is_err());
+    }
+
+    #[test]
+    fn long_and_double_take_two_pool_slots() {
+        let class = read_fixture(include_bytes!("../tests/fixtures/Wide.class")).unwrap();
+        let pool = &class.constant_pool;
+        assert_eq!(pool.len(), class.constant_pool_count as usize);
+        let long = pool
+            .iter()
+            .position(|entry| {
+                matches!(
+                    entry,
+                    ConstantPoolInfo::Long {
+                        bytes: 123456789012
+                    }
+                )
+            })
+            .unwrap();
+        assert!(matches!(pool[long + 1], ConstantPoolInfo::Unusable));
+        // entries after the long are referenced by their spec index
+        assert!(class.this_class as usize > long);
+        assert_eq!(class.name(), Some("Wide"));
+        assert!(!class.dump_constant_pool().contains("<invalid"));
+    }
+
+    #[test]
+    fn reference_to_wrong_tag_is_rejected() {
+        // javac puts Object.<init>'s Methodref first; point its class_index
+        // at the NameAndType instead of the Class
+        let mut bytes = PLAIN.to_vec();
+        assert_eq!(bytes[10], ConstantPoolTag::MethodRef as u8);
+        bytes.copy_within(13..15, 11);
+        assert!(read_fixture(&bytes).is_err());
+    }
+
+    #[test]
+    fn interfaces_are_read_as_indices() {
+        let class = read_fixture(TWO).unwrap();
+        assert_eq!(class.interfaces_count, 2);
+        assert_eq!(
+            class.interface_names(),
+            vec!["java/lang/Runnable", "java/lang/Cloneable"]
+        );
+        // the fields after the interface table are only right if it was
+        // read with the correct width
+        let field_names: Vec<_> = class
+            .fields
+            .iter()
+            .map(|field| class.get_utf8(field.name_index))
+            .collect();
+        assert_eq!(field_names, vec![Some("a"), Some("b"), Some("c")]);
+    }
+
+    #[test]
+    fn counts_match_and_stream_is_consumed() {
+        let mut reader = ClassFileReader::from_bytes(PLAIN.to_vec());
+        let plain = reader.read().unwrap();
+        assert!(reader.remaining().is_empty());
+        assert_eq!((plain.interfaces_count, plain.interfaces.len()), (0, 0));
+        assert_eq!((plain.fields_count, plain.fields.len()), (0, 0));
+        assert_eq!((plain.methods_count, plain.methods.len()), (1, 1));
+
+        let mut reader = ClassFileReader::from_bytes(TWO.to_vec());
+        let two = reader.read().unwrap();
+        assert!(reader.remaining().is_empty());
+        assert_eq!((two.interfaces_count, two.interfaces.len()), (2, 2));
+        assert_eq!((two.fields_count, two.fields.len()), (3, 3));
+        assert_eq!((two.methods_count, two.methods.len()), (3, 3));
+        assert_eq!(
+            two.interface_names(),
+            vec!["java/lang/Runnable", "java/lang/Cloneable"]
+        );
+    }
+
+    #[test]
+    fn trailing_bytes_are_rejected() {
+        let mut bytes = TWO.to_vec();
+        bytes.push(0);
+        assert!(read_fixture(&bytes).is_err());
+    }
+
+    #[test]
+    fn this_class_must_be_a_class() {
+        // entry #1 is a Methodref
+        let bytes = patch_u16(PLAIN, pool_end(PLAIN) + 2, 1);
+        assert!(read_fixture(&bytes).is_err());
+    }
+
+    #[test]
+    fn abstract_final_method_is_rejected() {
+        // an interface with no super-interfaces or fields, so its only
+        // method's flags follow the three counts
+        let flags = pool_end(ABS) + 12;
+        assert_eq!(&ABS[flags..flags + 2], &[0x04, 0x01]); // public abstract
+        assert!(read_fixture(ABS).is_ok());
+        assert!(read_fixture(&patch_u16(ABS, flags, 0x0411)).is_err());
+    }
+
+    #[test]
+    fn only_object_may_omit_super_class() {
+        let bytes = patch_u16(PLAIN, pool_end(PLAIN) + 4, 0);
+        assert!(read_fixture(&bytes).is_err());
+    }
+
+    #[test]
+    fn member_name_must_be_utf8() {
+        // Plain has no interfaces or fields; its constructor's name_index
+        // follows the three counts and its access flags
+        let name_index = pool_end(PLAIN) + 14;
+        assert!(read_fixture(&patch_u16(PLAIN, name_index, 0xffff)).is_err());
+        assert!(read_fixture(&patch_u16(PLAIN, name_index, 1)).is_err());
+    }
+
+    #[test]
+    fn errors_carry_their_cause() {
+        let mut bytes = PLAIN.to_vec();
+        bytes[0] = 0xBE;
+        let error = read_fixture(&bytes).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(error
+            .to_string()
+            .contains("Incompatible magic value 0xBEFEBABE"));
+        assert!(error.to_string().contains("at byte 4"));
+    }
+
+    #[test]
+    fn pretty_print_matches_snapshot() {
+        let class = read_fixture(PLAIN).unwrap();
+        assert_eq!(
+            class.pretty_print(),
+            include_str!("../tests/fixtures/Plain.txt")
+        );
+    }
+
+    #[test]
+    fn class_kind_helpers() {
+        let abs = read_fixture(ABS).unwrap();
+        assert!(abs.is_interface() && abs.is_abstract() && !abs.is_enum());
+        let plain = read_fixture(PLAIN).unwrap();
+        assert!(!plain.is_interface() && !plain.is_abstract() && !plain.is_enum());
+    }
+
+    #[test]
+    fn dump_constant_pool_lists_resolved_entries() {
+        let dump = read_fixture(PLAIN).unwrap().dump_constant_pool();
+        assert!(dump.lines().any(|line| line.trim()
+            == r#"#1 = Methodref          #2.#3          // java/lang/Object."<init>":()V"#));
+    }
+
+    const BRANCHY: &[u8] = include_bytes!("../tests/fixtures/Branchy.class");
+
+    #[test]
+    fn stack_map_frames_are_parsed() {
+        let class = read_fixture(BRANCHY).unwrap();
+        let code = class
+            .methods
+            .iter()
+            .find(|method| class.get_utf8(method.name_index) == Some("run"))
+            .and_then(|method| method.attributes.first())
+            .unwrap();
+        let Attribute::Code { attributes, .. } = &code.info else {
+            panic!("expected Code, got {:?}", code.info);
+        };
+        let entries = attributes
+            .iter()
+            .find_map(|attribute| match &attribute.info {
+                Attribute::StackMapTable { entries, .. } => Some(entries),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(entries.len(), 12);
+        assert!(matches!(
+            entries[0],
+            StackMapFrame::AppendFrame { frame_type: 253, ref locals, .. }
+                if matches!(locals[..], [VerificationTypeInfo::Long, VerificationTypeInfo::Integer])
+        ));
+        assert!(matches!(
+            entries[1],
+            StackMapFrame::ChopFrame {
+                frame_type: 250,
+                ..
+            }
+        ));
+        assert!(matches!(
+            entries[2],
+            StackMapFrame::SameFrame { frame_type: 36 }
+        ));
+        assert!(matches!(
+            entries[10],
+            StackMapFrame::SameLocals1StackItemFrame {
+                frame_type: 75,
+                stack: VerificationTypeInfo::Integer
+            }
+        ));
+        let StackMapFrame::FullFrame { locals, stack, .. } = &entries[11] else {
+            panic!("expected a full frame, got {:?}", entries[11]);
+        };
+        assert!(matches!(
+            locals[..],
+            [
+                VerificationTypeInfo::Integer,
+                VerificationTypeInfo::Object { .. },
+                VerificationTypeInfo::Long
+            ]
+        ));
+        assert!(matches!(
+            stack[..],
+            [VerificationTypeInfo::Integer, VerificationTypeInfo::Integer]
+        ));
+    }
+
+    #[test]
+    fn reserved_stack_map_frame_type_is_rejected() {
+        let class = read_fixture(BRANCHY).unwrap();
+        let name = class
+            .constant_pool
+            .iter()
+            .position(|entry| matches!(entry, ConstantPoolInfo::Utf8 { string } if string == "StackMapTable"))
+            .unwrap() as u16;
+        // attribute_name_index, attribute_length, number_of_entries (12),
+        // then the first frame's type (append, 253)
+        let at = BRANCHY
+            .windows(9)
+            .position(|w| w[0..2] == name.to_be_bytes() && w[6..8] == [0, 12] && w[8] == 253)
+            .unwrap()
+            + 8;
+        let mut bytes = BRANCHY.to_vec();
+        bytes[at] = 128;
+        let error = read_fixture(&bytes).unwrap_err();
+        assert!(error.to_string().contains("reserved type '128'"));
+    }
+
+    #[test]
+    fn illegal_field_and_visibility_flags_are_rejected() {
+        // Two's first field follows its two interfaces and the fields count
+        let field = pool_end(TWO) + 14;
+        assert_eq!(&TWO[field..field + 2], &[0, 0]);
+        assert!(read_fixture(&patch_u16(TWO, field, 0x0010)).is_ok()); // final
+        assert!(read_fixture(&patch_u16(TWO, field, 0x0050)).is_err()); // final volatile
+        assert!(read_fixture(&patch_u16(TWO, field, 0x0003)).is_err()); // public private
+        let method = pool_end(PLAIN) + 12;
+        assert!(read_fixture(&patch_u16(PLAIN, method, 0x0005)).is_err()); // public protected
+    }
+
+    const BRIDGE: &[u8] = include_bytes!("../tests/fixtures/Bridge.class");
+
+    #[test]
+    fn bridge_method_is_synthetic() {
+        let class = read_fixture(BRIDGE).unwrap();
+        let synthetic: Vec<_> = class
+            .methods
+            .iter()
+            .map(|method| {
+                (
+                    class.get_utf8(method.descriptor_index).unwrap(),
+                    method.is_synthetic(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            synthetic,
+            vec![
+                ("()V", false),
+                ("()Ljava/lang/String;", false),
+                ("()Ljava/lang/Object;", true)
+            ]
+        );
+        assert!(!class.is_synthetic());
+        assert!(class.fields.iter().all(|field| !field.is_synthetic()));
+    }
+
+    #[test]
+    fn synthetic_attribute_marks_the_class() {
+        let class = read_fixture(BRIDGE).unwrap();
+        let name = class
+            .constant_pool
+            .iter()
+            .position(
+                |entry| matches!(entry, ConstantPoolInfo::Utf8 { string } if string == "Synthetic"),
+            )
+            .unwrap() as u16;
+        // the class ends with its only attribute, SourceFile: name, length 2, index
+        let end = BRIDGE.len();
+        assert_eq!(&BRIDGE[end - 10..end - 8], &[0, 1]);
+        let mut bytes = BRIDGE[..end - 8].to_vec();
+        bytes.extend_from_slice(&name.to_be_bytes());
+        bytes.extend_from_slice(&0u32.to_be_bytes());
+        assert!(read_fixture(&bytes).unwrap().is_synthetic());
+
+        // a non-empty Synthetic would leave its payload to be misread
+        let mut bytes = BRIDGE.to_vec();
+        bytes[end - 8..end - 6].copy_from_slice(&name.to_be_bytes());
+        let error = read_fixture(&bytes).unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("Synthetic attribute has length 2"));
+    }
+
+    const CONSTANTS: &[u8] = include_bytes!("../tests/fixtures/Constants.class");
+
+    #[test]
+    fn constant_values_resolve() {
+        let class = read_fixture(CONSTANTS).unwrap();
+        let values: Vec<_> = class
+            .fields
+            .iter()
+            .map(|field| match field.attributes[..] {
+                [AttributeInfo {
+                    info:
+                        Attribute::ConstantValue {
+                            constantvalue_index,
+                        },
+                    ..
+                }] => &class.constant_pool[constantvalue_index as usize],
+                _ => panic!("expected a single ConstantValue on {:?}", field),
+            })
+            .collect();
+        assert!(matches!(
+            values[..],
+            [
+                ConstantPoolInfo::Long { bytes: 123456789012 },
+                ConstantPoolInfo::Double { bytes },
+                ConstantPoolInfo::String { .. },
+            ] if *bytes == 2.5
+        ));
+    }
+
+    #[test]
+    fn constant_value_must_be_loadable() {
+        let class = read_fixture(CONSTANTS).unwrap();
+        let Attribute::ConstantValue {
+            constantvalue_index,
+        } = class.fields[0].attributes[0].info
+        else {
+            unreachable!()
+        };
+        let name = class.fields[0].attributes[0].attribute_name_index;
+        // attribute_name_index, attribute_length 2, constantvalue_index
+        let mut attribute = name.to_be_bytes().to_vec();
+        attribute.extend_from_slice(&2u32.to_be_bytes());
+        attribute.extend_from_slice(&constantvalue_index.to_be_bytes());
+        let at = CONSTANTS
+            .windows(attribute.len())
+            .position(|window| window == attribute)
+            .unwrap();
+        let bytes = patch_u16(CONSTANTS, at + 6, class.this_class);
+        let error = read_fixture(&bytes).unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("Expected loadable constant for ConstantValue"));
+    }
+
+    #[test]
+    fn truncated_and_mutated_input_never_panics() {
+        for len in 0..TWO.len() {
+            assert!(read_fixture(&TWO[..len]).is_err());
+        }
+        // xorshift, so failures are reproducible
+        let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+        for _ in 0..20_000 {
+            let mut bytes = TWO.to_vec();
+            for _ in 0..1 + next() % 4 {
+                let at = next() as usize % bytes.len();
+                bytes[at] = next() as u8;
+            }
+            let _ = read_fixture(&bytes);
+        }
+    }
+}
@@ -1,21 +1,169 @@
-use std::collections::VecDeque;
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    io::{self, Cursor},
+    path::PathBuf,
+    rc::Rc,
+};
 
+use byteorder::{BigEndian, ReadBytesExt};
 use num_enum::{IntoPrimitive, TryFromPrimitive};
 
-use crate::reader::ConstantPoolInfo;
+use crate::reader::{ClassFile, ClassFileReader, ConstantPoolInfo};
 
+#[allow(dead_code, clippy::upper_case_acronyms)]
 struct JVM<'a> {
     class_loader: ClassLoader,
     runtime: Runtime<'a>,
 }
 
-struct ClassLoader {}
+#[allow(dead_code)]
+impl<'a> JVM<'a> {
+    /// Names of every class loaded so far, in internal (`com/foo/Bar`) form.
+    pub fn classes(&self) -> impl Iterator<Item = &str> {
+        self.class_loader.classes()
+    }
+}
+
+/// Supplies raw class file bytes from somewhere other than the filesystem
+/// (a database, the network, a generator, ...).
+#[allow(dead_code)]
+pub trait ClassProvider {
+    fn find(&self, name: &str) -> Option<Vec<u8>>;
+}
+
+#[allow(dead_code)]
+type ClassLoadedHook = Box<dyn FnMut(&ClassFile)>;
+
+#[allow(dead_code)]
+struct ClassLoader {
+    provider: Option<Box<dyn ClassProvider>>,
+    base_path: PathBuf,
+    classes: HashMap<String, Rc<ClassFile>>,
+    loading: HashSet<String>,
+    on_class_loaded: Option<ClassLoadedHook>,
+}
+
+#[allow(dead_code)]
+impl ClassLoader {
+    pub fn new() -> Self {
+        Self {
+            provider: None,
+            base_path: PathBuf::from("."),
+            classes: HashMap::new(),
+            loading: HashSet::new(),
+            on_class_loaded: None,
+        }
+    }
+
+    pub fn set_provider(&mut self, provider: Box<dyn ClassProvider>) {
+        self.provider = Some(provider);
+    }
+
+    /// Directory that class files are resolved against instead of the process cwd.
+    pub fn set_base_path(&mut self, base_path: impl Into<PathBuf>) {
+        self.base_path = base_path.into();
+    }
+
+    /// Called once for every class after it has been defined, superclasses first.
+    pub fn set_on_class_loaded(&mut self, hook: ClassLoadedHook) {
+        self.on_class_loaded = Some(hook);
+    }
+
+    pub fn classes(&self) -> impl Iterator<Item = &str> {
+        self.classes.keys().map(String::as_str)
+    }
+
+    /// Loads `name` (and its superclass and interfaces), checking it against
+    /// any sealed parent. Dotted (`com.foo.Bar`) and internal (`com/foo/Bar`)
+    /// names resolve to the same class.
+    pub fn load_class(&mut self, name: &str) -> io::Result<Rc<ClassFile>> {
+        let name = name.replace('.', "/");
+        if let Some(class) = self.classes.get(&name) {
+            return Ok(Rc::clone(class));
+        }
+        if !self.loading.insert(name.clone()) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("ClassCircularityError: {}", name),
+            ));
+        }
+        let class = self.define_class(&name);
+        self.loading.remove(&name);
+        class
+    }
+
+    fn define_class(&mut self, name: &str) -> io::Result<Rc<ClassFile>> {
+        let class = self.read_class(name)?;
+        if class.name() != Some(name) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "NoClassDefFoundError: {} (wrong name: {})",
+                    name,
+                    class.name().unwrap_or("<invalid>")
+                ),
+            ));
+        }
+        let parents: Vec<String> = class
+            .super_class_name()
+            .into_iter()
+            .chain(class.interface_names())
+            .map(str::to_string)
+            .collect();
+        for parent_name in parents {
+            let parent = match self.load_class(&parent_name) {
+                Ok(parent) => parent,
+                // There is no class library to bootstrap from yet, so parents
+                // that can't be found (e.g. java/lang/Object) are skipped.
+                Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e),
+            };
+            Self::check_sealed_parent(name, &parent_name, &parent)?;
+        }
+
+        let class = Rc::new(class);
+        self.classes.insert(name.to_string(), Rc::clone(&class));
+        if let Some(hook) = self.on_class_loaded.as_mut() {
+            hook(&class);
+        }
+        Ok(class)
+    }
+
+    /// Reads `name` from the provider if one is set and knows the class,
+    /// falling back to `<base_path>/<name>.class` on disk.
+    fn read_class(&self, name: &str) -> io::Result<ClassFile> {
+        let mut reader = match self.provider.as_ref().and_then(|p| p.find(name)) {
+            Some(bytes) => ClassFileReader::from_bytes(bytes),
+            None => ClassFileReader::new(self.base_path.join(format!("{}.class", name)))?,
+        };
+        reader.read()
+    }
+
+    /// A class whose direct superclass or superinterface is sealed must be
+    /// listed in that parent's `PermittedSubclasses`.
+    fn check_sealed_parent(name: &str, parent_name: &str, parent: &ClassFile) -> io::Result<()> {
+        if let Some(permitted) = parent.permitted_subclasses() {
+            if !permitted.contains(&name) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "IncompatibleClassChangeError: class {} is not a permitted subclass of sealed class {}",
+                        name, parent_name
+                    ),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
 
+#[allow(dead_code)]
 struct Runtime<'a> {
     stack: VecDeque<StackFrame<'a>>,
     opcodes: Vec<Opcode>,
 }
 
+#[allow(dead_code)]
 impl<'a> Runtime<'a> {
     pub fn new() -> Self {
         Self {
@@ -25,16 +173,72 @@ impl<'a> Runtime<'a> {
     }
 }
 
+#[allow(dead_code)]
 struct Interpreter {}
 
+#[allow(dead_code)]
 type ConstantPool = Vec<ConstantPoolInfo>;
 
+#[allow(dead_code)]
 struct StackFrame<'a> {
     locals: Vec<JValue>,
     operand_stack: VecDeque<JValue>,
+    /// Operand stack depth in slots, where long and double count as two.
+    stack_depth: usize,
     constant_pool: &'a ConstantPool,
 }
 
+#[allow(dead_code)]
+impl<'a> StackFrame<'a> {
+    pub fn push(&mut self, value: JValue) {
+        self.stack_depth += value.slot_count();
+        self.operand_stack.push_back(value);
+    }
+
+    pub fn pop(&mut self) -> Option<JValue> {
+        let value = self.operand_stack.pop_back()?;
+        self.stack_depth -= value.slot_count();
+        Some(value)
+    }
+
+    pub fn push_long(&mut self, value: i64) {
+        self.push(JValue::Long(value));
+    }
+
+    pub fn push_double(&mut self, value: f64) {
+        self.push(JValue::Double(value));
+    }
+
+    /// Pops a long, leaving the stack untouched if the top value isn't one.
+    pub fn pop_long(&mut self) -> Option<i64> {
+        match self.operand_stack.back()? {
+            &JValue::Long(value) => {
+                self.pop();
+                Some(value)
+            }
+            _ => None,
+        }
+    }
+
+    /// Pops a double, leaving the stack untouched if the top value isn't one.
+    pub fn pop_double(&mut self) -> Option<f64> {
+        match self.operand_stack.back()? {
+            &JValue::Double(value) => {
+                self.pop();
+                Some(value)
+            }
+            _ => None,
+        }
+    }
+
+    /// Depth as the verifier and `max_stack` see it, not the number of values.
+    pub fn stack_depth_slots(&self) -> usize {
+        self.stack_depth
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, PartialEq)]
 enum JValue {
     Byte(i8),
     Short(i16),
@@ -47,9 +251,25 @@ enum JValue {
     // impl more
 }
 
-#[derive(Debug, IntoPrimitive, TryFromPrimitive)]
+#[allow(dead_code)]
+impl JValue {
+    pub fn is_category2(&self) -> bool {
+        matches!(self, JValue::Long(_) | JValue::Double(_))
+    }
+
+    /// Number of local variable / operand stack slots the value occupies.
+    pub fn slot_count(&self) -> usize {
+        if self.is_category2() {
+            2
+        } else {
+            1
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, IntoPrimitive, TryFromPrimitive)]
 #[repr(u8)]
-enum Opcode {
+pub enum Opcode {
     // Constants
     Nop,
     AConstNull,
@@ -263,6 +483,607 @@ enum Opcode {
     JsrW,
     // Reserved
     Breakpoint,
-    ImpDep1,
-    ImpDep2,
+    ImpDep1 = 0xFE,
+    ImpDep2 = 0xFF,
+}
+
+/// Operands of a tableswitch or lookupswitch instruction.
+#[derive(Debug)]
+pub enum SwitchTable {
+    Table {
+        default: i32,
+        low: i32,
+        high: i32,
+        offsets: Vec<i32>,
+    },
+    Lookup {
+        default: i32,
+        pairs: Vec<(i32, i32)>,
+    },
+}
+
+impl SwitchTable {
+    /// Decodes the switch instruction at `pc`, returning it along with the
+    /// instruction's total length. Rejects a tableswitch with `low > high` and
+    /// a lookupswitch whose keys aren't sorted in strictly ascending order.
+    pub fn read(code: &[u8], pc: usize) -> io::Result<(SwitchTable, usize)> {
+        let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+        let mut cur = Cursor::new(code);
+        // operands start at the next multiple of 4 after the opcode
+        cur.set_position((pc as u64 + 4) & !3);
+        let opcode = Opcode::from_byte(code[pc])?;
+        let default = cur.read_i32::<BigEndian>()?;
+        let table = match opcode {
+            Opcode::TableSwitch => {
+                let low = cur.read_i32::<BigEndian>()?;
+                let high = cur.read_i32::<BigEndian>()?;
+                if low > high {
+                    return Err(invalid(format!(
+                        "tableswitch at {} has low {} greater than high {}",
+                        pc, low, high
+                    )));
+                }
+                let mut offsets = Vec::new();
+                for _ in low as i64..=high as i64 {
+                    offsets.push(cur.read_i32::<BigEndian>()?);
+                }
+                SwitchTable::Table {
+                    default,
+                    low,
+                    high,
+                    offsets,
+                }
+            }
+            Opcode::LookupSwitch => {
+                let npairs = cur.read_i32::<BigEndian>()?;
+                if npairs < 0 {
+                    return Err(invalid(format!(
+                        "lookupswitch at {} has negative npairs {}",
+                        pc, npairs
+                    )));
+                }
+                let mut pairs: Vec<(i32, i32)> = Vec::new();
+                for _ in 0..npairs {
+                    let key = cur.read_i32::<BigEndian>()?;
+                    let offset = cur.read_i32::<BigEndian>()?;
+                    if let Some(&(previous, _)) = pairs.last() {
+                        if key <= previous {
+                            return Err(invalid(format!(
+                                "lookupswitch at {} keys are not sorted ({} after {})",
+                                pc, key, previous
+                            )));
+                        }
+                    }
+                    pairs.push((key, offset));
+                }
+                SwitchTable::Lookup { default, pairs }
+            }
+            other => {
+                return Err(invalid(format!(
+                    "Expected tableswitch or lookupswitch, received '{}'",
+                    other.name()
+                )))
+            }
+        };
+        Ok((table, cur.position() as usize - pc))
+    }
+}
+
+/// javap-style mnemonics for opcodes 0x00 (nop) through 0xCA (breakpoint).
+const MNEMONICS: [&str; 0xCB] = [
+    // Constants
+    "nop",
+    "aconst_null",
+    "iconst_m1",
+    "iconst_0",
+    "iconst_1",
+    "iconst_2",
+    "iconst_3",
+    "iconst_4",
+    "iconst_5",
+    "lconst_0",
+    "lconst_1",
+    "fconst_0",
+    "fconst_1",
+    "fconst_2",
+    "dconst_0",
+    "dconst_1",
+    "bipush",
+    "sipush",
+    "ldc",
+    "ldc_w",
+    "ldc2_w",
+    // Loads
+    "iload",
+    "lload",
+    "fload",
+    "dload",
+    "aload",
+    "iload_0",
+    "iload_1",
+    "iload_2",
+    "iload_3",
+    "lload_0",
+    "lload_1",
+    "lload_2",
+    "lload_3",
+    "fload_0",
+    "fload_1",
+    "fload_2",
+    "fload_3",
+    "dload_0",
+    "dload_1",
+    "dload_2",
+    "dload_3",
+    "aload_0",
+    "aload_1",
+    "aload_2",
+    "aload_3",
+    "iaload",
+    "laload",
+    "faload",
+    "daload",
+    "aaload",
+    "baload",
+    "caload",
+    "saload",
+    // Stores
+    "istore",
+    "lstore",
+    "fstore",
+    "dstore",
+    "astore",
+    "istore_0",
+    "istore_1",
+    "istore_2",
+    "istore_3",
+    "lstore_0",
+    "lstore_1",
+    "lstore_2",
+    "lstore_3",
+    "fstore_0",
+    "fstore_1",
+    "fstore_2",
+    "fstore_3",
+    "dstore_0",
+    "dstore_1",
+    "dstore_2",
+    "dstore_3",
+    "astore_0",
+    "astore_1",
+    "astore_2",
+    "astore_3",
+    "iastore",
+    "lastore",
+    "fastore",
+    "dastore",
+    "aastore",
+    "bastore",
+    "castore",
+    "sastore",
+    // Stack
+    "pop",
+    "pop2",
+    "dup",
+    "dup_x1",
+    "dup_x2",
+    "dup2",
+    "dup2_x1",
+    "dup2_x2",
+    "swap",
+    // Math
+    "iadd",
+    "ladd",
+    "fadd",
+    "dadd",
+    "isub",
+    "lsub",
+    "fsub",
+    "dsub",
+    "imul",
+    "lmul",
+    "fmul",
+    "dmul",
+    "idiv",
+    "ldiv",
+    "fdiv",
+    "ddiv",
+    "irem",
+    "lrem",
+    "frem",
+    "drem",
+    "ineg",
+    "lneg",
+    "fneg",
+    "dneg",
+    "ishl",
+    "lshl",
+    "ishr",
+    "lshr",
+    "iushr",
+    "lushr",
+    "iand",
+    "land",
+    "ior",
+    "lor",
+    "ixor",
+    "lxor",
+    "iinc",
+    // Conversions
+    "i2l",
+    "i2f",
+    "i2d",
+    "l2i",
+    "l2f",
+    "l2d",
+    "f2i",
+    "f2l",
+    "f2d",
+    "d2i",
+    "d2l",
+    "d2f",
+    "i2b",
+    "i2c",
+    "i2s",
+    "lcmp",
+    "fcmpl",
+    "fcmpg",
+    "dcmpl",
+    "dcmpg",
+    "ifeq",
+    "ifne",
+    "iflt",
+    "ifge",
+    "ifgt",
+    "ifle",
+    "if_icmpeq",
+    "if_icmpne",
+    "if_icmplt",
+    "if_icmpge",
+    "if_icmpgt",
+    "if_icmple",
+    "if_acmpeq",
+    "if_acmpne",
+    // Control
+    "goto",
+    "jsr",
+    "ret",
+    "tableswitch",
+    "lookupswitch",
+    "ireturn",
+    "lreturn",
+    "freturn",
+    "dreturn",
+    "areturn",
+    "return",
+    // References
+    "getstatic",
+    "putstatic",
+    "getfield",
+    "putfield",
+    "invokevirtual",
+    "invokespecial",
+    "invokestatic",
+    "invokeinterface",
+    "invokedynamic",
+    "new",
+    "newarray",
+    "anewarray",
+    "arraylength",
+    "athrow",
+    "checkcast",
+    "instanceof",
+    "monitorenter",
+    "monitorexit",
+    // Extended
+    "wide",
+    "multianewarray",
+    "ifnull",
+    "ifnonnull",
+    "goto_w",
+    "jsr_w",
+    // Reserved
+    "breakpoint",
+];
+
+impl Opcode {
+    /// Length in bytes of the instruction at `pc`, counting the opcode, its
+    /// operands and any switch padding. Fails if the instruction is malformed
+    /// or runs past the end of `code`.
+    pub fn instruction_length(code: &[u8], pc: usize) -> io::Result<usize> {
+        use Opcode as O;
+        let byte_at = |pc: usize| {
+            code.get(pc).copied().ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "Instruction runs past end of code",
+                )
+            })
+        };
+        let byte = byte_at(pc)?;
+        let length = match Opcode::from_byte(byte)? {
+            O::TableSwitch | O::LookupSwitch => {
+                return SwitchTable::read(code, pc).map(|(_, len)| len)
+            }
+            O::Wide => match Opcode::from_byte(byte_at(pc + 1)?)? {
+                O::IInc => 6,
+                O::ILoad
+                | O::LLoad
+                | O::FLoad
+                | O::DLoad
+                | O::ALoad
+                | O::IStore
+                | O::LStore
+                | O::FStore
+                | O::DStore
+                | O::AStore
+                | O::Ret => 4,
+                other => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("Opcode '{}' can't be widened", other.name()),
+                    ))
+                }
+            },
+            _ => 1 + Opcode::operand_len(byte).expect("variable-length opcodes are handled above"),
+        };
+        if pc + length > code.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "Instruction runs past end of code",
+            ));
+        }
+        Ok(length)
+    }
+
+    /// javap's mnemonic for a raw opcode byte, or `None` if the byte can't
+    /// appear in a class file (unassigned or reserved).
+    pub fn mnemonic(byte: u8) -> Option<&'static str> {
+        Opcode::from_byte(byte).ok().map(Opcode::name)
+    }
+
+    /// Number of operand bytes following a fixed-length opcode, without
+    /// decoding them. `None` for bytes that aren't valid opcodes and for
+    /// tableswitch, lookupswitch and wide, whose length depends on the bytes
+    /// that follow; use [`Opcode::instruction_length`] to step over those.
+    pub fn operand_len(byte: u8) -> Option<usize> {
+        use Opcode as O;
+        let length = match Opcode::from_byte(byte).ok()? {
+            O::TableSwitch | O::LookupSwitch | O::Wide => return None,
+            O::BIPush
+            | O::Ldc
+            | O::ILoad
+            | O::LLoad
+            | O::FLoad
+            | O::DLoad
+            | O::ALoad
+            | O::IStore
+            | O::LStore
+            | O::FStore
+            | O::DStore
+            | O::AStore
+            | O::Ret
+            | O::NewArray => 1,
+            O::SIPush
+            | O::LdcW
+            | O::Ldc2W
+            | O::IInc
+            | O::IfEq
+            | O::IfNe
+            | O::IfLt
+            | O::IfGe
+            | O::IfGt
+            | O::IfLe
+            | O::IfICmpEq
+            | O::IfICmpNe
+            | O::IfICmpLt
+            | O::IfICmpGe
+            | O::IfICmpGt
+            | O::IfICmpLe
+            | O::IfACmpEq
+            | O::IfACmpNe
+            | O::Goto
+            | O::Jsr
+            | O::IfNull
+            | O::IfNonNull
+            | O::GetStatic
+            | O::PutStatic
+            | O::GetField
+            | O::PutField
+            | O::InvokeVirtual
+            | O::InvokeSpecial
+            | O::InvokeStatic
+            | O::New
+            | O::ANewArray
+            | O::CheckCast
+            | O::InstanceOf => 2,
+            O::MultiANewArray => 3,
+            O::InvokeInterface | O::InvokeDynamic | O::GotoW | O::JsrW => 4,
+            _ => 0,
+        };
+        Some(length)
+    }
+
+    /// The instruction's mnemonic as written by javap, e.g. `if_icmpeq`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Opcode::ImpDep1 => "impdep1",
+            Opcode::ImpDep2 => "impdep2",
+            other => MNEMONICS[u8::from(other) as usize],
+        }
+    }
+
+    /// Decodes an opcode byte from a method's code. The reserved opcodes
+    /// (breakpoint, impdep1, impdep2) must never appear in a class file and
+    /// 0xCB..=0xFD are unassigned, so both are rejected.
+    pub fn from_byte(byte: u8) -> io::Result<Opcode> {
+        match Opcode::try_from(byte) {
+            Ok(op @ (Opcode::Breakpoint | Opcode::ImpDep1 | Opcode::ImpDep2)) => {
+                Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Reserved opcode {:?} (0x{:02X}) in method code", op, byte),
+                ))
+            }
+            Ok(op) => Ok(op),
+            Err(_) => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Unknown opcode 0x{:02X}", byte),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Fixtures(HashMap<&'static str, &'static [u8]>);
+
+    impl ClassProvider for Fixtures {
+        fn find(&self, name: &str) -> Option<Vec<u8>> {
+            self.0.get(name).map(|bytes| bytes.to_vec())
+        }
+    }
+
+    fn loader(classes: &[(&'static str, &'static [u8])]) -> ClassLoader {
+        let mut loader = ClassLoader::new();
+        loader.set_provider(Box::new(Fixtures(classes.iter().copied().collect())));
+        loader
+    }
+
+    fn frame(constant_pool: &ConstantPool) -> StackFrame<'_> {
+        StackFrame {
+            locals: Vec::new(),
+            operand_stack: VecDeque::new(),
+            stack_depth: 0,
+            constant_pool,
+        }
+    }
+
+    #[test]
+    fn stack_depth_counts_category2_as_two_slots() {
+        let pool = ConstantPool::new();
+        let mut frame = frame(&pool);
+        frame.push(JValue::Long(1));
+        frame.push(JValue::Int(2));
+        assert_eq!(frame.stack_depth_slots(), 3);
+        assert_eq!(frame.pop(), Some(JValue::Int(2)));
+        assert_eq!(frame.stack_depth_slots(), 2);
+        assert_eq!(frame.pop(), Some(JValue::Long(1)));
+        assert_eq!(frame.stack_depth_slots(), 0);
+        assert_eq!(frame.pop(), None);
+    }
+
+    #[test]
+    fn typed_long_and_double_helpers_keep_depth() {
+        let pool = ConstantPool::new();
+        let mut frame = frame(&pool);
+        frame.push_long(i64::MIN);
+        frame.push_double(0.5);
+        assert_eq!(frame.stack_depth_slots(), 4);
+        assert_eq!(frame.pop_long(), None);
+        assert_eq!(frame.stack_depth_slots(), 4);
+        assert_eq!(frame.pop_double(), Some(0.5));
+        assert_eq!(frame.pop_long(), Some(i64::MIN));
+        assert_eq!(frame.stack_depth_slots(), 0);
+    }
+
+    /// A switch at pc 0: the opcode, three bytes of padding, then `operands`.
+    fn switch(opcode: Opcode, operands: &[i32]) -> Vec<u8> {
+        let mut code = vec![u8::from(opcode), 0, 0, 0];
+        for operand in operands {
+            code.extend_from_slice(&operand.to_be_bytes());
+        }
+        code
+    }
+
+    #[test]
+    fn raw_opcode_bytes() {
+        assert_eq!(Opcode::mnemonic(0x60), Some("iadd"));
+        assert_eq!(Opcode::mnemonic(0xfe), None);
+        assert_eq!(Opcode::mnemonic(0xcb), None);
+        assert_eq!(Opcode::operand_len(0x10), Some(1)); // bipush
+        assert_eq!(Opcode::operand_len(0xb9), Some(4)); // invokeinterface
+        assert_eq!(Opcode::operand_len(0x60), Some(0));
+        assert_eq!(Opcode::operand_len(0xc4), None); // wide
+        assert_eq!(Opcode::operand_len(0xaa), None); // tableswitch
+        assert_eq!(Opcode::operand_len(0xcb), None);
+    }
+
+    #[test]
+    fn tableswitch_operands() {
+        let code = switch(Opcode::TableSwitch, &[20, 1, 2, 8, 12]);
+        assert_eq!(Opcode::instruction_length(&code, 0).unwrap(), code.len());
+        let reversed = switch(Opcode::TableSwitch, &[20, 2, 1, 8, 12]);
+        assert!(SwitchTable::read(&reversed, 0).is_err());
+        // a table that claims more entries than the code holds
+        assert!(Opcode::instruction_length(&code[..code.len() - 1], 0).is_err());
+    }
+
+    #[test]
+    fn lookupswitch_keys_must_be_sorted() {
+        let code = switch(Opcode::LookupSwitch, &[20, 2, -5, 8, 7, 12]);
+        assert!(matches!(
+            SwitchTable::read(&code, 0).unwrap(),
+            (SwitchTable::Lookup { default: 20, ref pairs }, 28) if pairs == &[(-5, 8), (7, 12)]
+        ));
+        let unsorted = switch(Opcode::LookupSwitch, &[20, 2, 7, 8, -5, 12]);
+        assert!(SwitchTable::read(&unsorted, 0).is_err());
+        let negative = switch(Opcode::LookupSwitch, &[20, -1]);
+        assert!(SwitchTable::read(&negative, 0).is_err());
+    }
+
+    const SEALED: &[u8] = include_bytes!("../../tests/fixtures/sealed/S.class");
+    const PERMITTED: &[u8] = include_bytes!("../../tests/fixtures/sealed/A.class");
+    const NOT_PERMITTED: &[u8] = include_bytes!("../../tests/fixtures/sealed/B.class");
+
+    #[test]
+    fn sealed_parent_only_accepts_permitted_subclasses() {
+        let mut loader = loader(&[("S", SEALED), ("A", PERMITTED), ("B", NOT_PERMITTED)]);
+        assert!(loader.load_class("A").is_ok());
+        let error = loader.load_class("B").unwrap_err();
+        assert!(error.to_string().contains("IncompatibleClassChangeError"));
+        assert!(!loader.classes().any(|name| name == "B"));
+    }
+
+    #[test]
+    fn dotted_and_internal_names_share_a_class() {
+        let mut loader = ClassLoader::new();
+        loader.set_base_path("tests/fixtures");
+        let dotted = loader.load_class("pkg.Foo").unwrap();
+        let internal = loader.load_class("pkg/Foo").unwrap();
+        assert!(Rc::ptr_eq(&dotted, &internal));
+        assert_eq!(loader.classes().collect::<Vec<_>>(), vec!["pkg/Foo"]);
+    }
+
+    #[test]
+    fn hook_sees_superclass_first_and_classes_are_cached() {
+        let mut loader = loader(&[("S", SEALED), ("A", PERMITTED)]);
+        let seen = Rc::new(std::cell::RefCell::new(Vec::new()));
+        let log = Rc::clone(&seen);
+        loader.set_on_class_loaded(Box::new(move |class| {
+            log.borrow_mut().push(class.name().unwrap().to_string())
+        }));
+        let first = loader.load_class("A").unwrap();
+        assert_eq!(*seen.borrow(), vec!["S", "A"]);
+        let mut names: Vec<_> = loader.classes().collect();
+        names.sort();
+        assert_eq!(names, vec!["A", "S"]);
+
+        let second = loader.load_class("A").unwrap();
+        assert!(Rc::ptr_eq(&first, &second));
+        assert_eq!(seen.borrow().len(), 2);
+    }
+
+    #[test]
+    fn class_must_match_the_requested_name() {
+        let mut loader = loader(&[("Synth", PERMITTED)]);
+        let loaded = Rc::new(std::cell::Cell::new(0));
+        let count = Rc::clone(&loaded);
+        loader.set_on_class_loaded(Box::new(move |_| count.set(count.get() + 1)));
+        let error = loader.load_class("Synth").unwrap_err();
+        assert!(error.to_string().contains("wrong name: A"));
+        assert_eq!(loader.classes().count(), 0);
+        assert_eq!(loaded.get(), 0);
+    }
 }
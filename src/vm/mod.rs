@@ -1 +1 @@
-mod jvm;
+pub mod jvm;
@@ -0,0 +1,28 @@
+use std::process::Command;
+
+fn dump_class(path: &str) -> std::process::Output {
+    Command::new(env!("CARGO_BIN_EXE_jvm-r"))
+        .args(["--dump-class", path])
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn dumps_a_class() {
+    let output = dump_class("tests/fixtures/Two.class");
+    assert!(output.status.success());
+    let listing = String::from_utf8(output.stdout).unwrap();
+    assert!(listing.starts_with("class Two"));
+    assert!(listing.contains("  sum\n    descriptor: (ILjava/lang/String;)I"));
+}
+
+#[test]
+fn reports_unreadable_classes_without_panicking() {
+    for path in ["tests/fixtures/Missing.class", "tests/fixtures/Two.java"] {
+        let output = dump_class(path);
+        assert_eq!(output.status.code(), Some(1));
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        assert!(stderr.contains("Error reading"));
+        assert!(!stderr.contains("panicked"));
+    }
+}
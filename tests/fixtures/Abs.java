@@ -0,0 +1,3 @@
+public interface Abs {
+    void m();
+}
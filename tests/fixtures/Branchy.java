@@ -0,0 +1,22 @@
+public class Branchy {
+    static int run(int x, Object o) {
+        long total = 0;
+        for (int i = 0; i < x; i++) {
+            total += i;
+        }
+        switch (x) {
+            case 1: return 1;
+            case 5: return 2;
+            case 1000: return 3;
+        }
+        switch (x) {
+            case 1: case 2: case 3: x++; break;
+            default: x--;
+        }
+        if (o instanceof String) {
+            String s = (String) o;
+            x += s.length();
+        }
+        return (int) total + x + (o == null ? 0 : 1);
+    }
+}
@@ -0,0 +1,14 @@
+class Base {
+    Object get() {
+        return null;
+    }
+}
+
+// The covariant override makes javac emit a synthetic bridge `Object get()`.
+// The "Synthetic" literal puts that Utf8 in the pool so tests can retarget
+// an attribute name at it.
+public class Bridge extends Base {
+    String get() {
+        return "Synthetic";
+    }
+}
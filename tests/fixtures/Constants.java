@@ -0,0 +1,5 @@
+public class Constants {
+    static final long BIG = 123456789012L;
+    static final double RATIO = 2.5;
+    static final String NAME = "hi";
+}
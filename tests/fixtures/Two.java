@@ -0,0 +1,12 @@
+public class Two implements Runnable, Cloneable {
+    int a;
+    long b;
+    String c;
+
+    public void run() {
+    }
+
+    public int sum(int x, String label) {
+        return x + a;
+    }
+}
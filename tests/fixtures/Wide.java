@@ -0,0 +1,5 @@
+public class Wide {
+    static long big = 123456789012L;
+    static double ratio = 2.5;
+    static int after = 7;
+}
@@ -0,0 +1,3 @@
+module fixture {
+    requires java.logging;
+}
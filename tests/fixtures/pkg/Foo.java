@@ -0,0 +1,4 @@
+package pkg;
+
+public class Foo {
+}
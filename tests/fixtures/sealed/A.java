@@ -0,0 +1,2 @@
+public final class A extends S {
+}
@@ -0,0 +1,3 @@
+// Compiled against a non-sealed S so that javac accepts it.
+public class B extends S {
+}
@@ -0,0 +1,2 @@
+public sealed class S permits A {
+}